@@ -0,0 +1,51 @@
+use olive_rs::{Canvas, Event, HeapPixels2D, Pixel, PixelPointF, Pixels2D, Render};
+use wasm::start_render_resizable;
+
+const BACKGROUND_COLOR: Pixel = Pixel::new(0x20, 0x20, 0x20, 0xff);
+const CIRCLE_COLOR: Pixel = Pixel::new(0, 0, 0xaa, 0x99);
+
+fn main() {
+    start_render_resizable(FollowPointer::new());
+}
+
+/// A circle following the pointer in a canvas filling the window
+struct FollowPointer {
+    pixels: HeapPixels2D,
+    pointer: (f64, f64),
+}
+
+impl FollowPointer {
+    fn new() -> Self {
+        // Reallocated on the first resize
+        let pixels = HeapPixels2D::new(1, 1, BACKGROUND_COLOR);
+        Self {
+            pixels,
+            pointer: (0., 0.),
+        }
+    }
+}
+
+impl Render for FollowPointer {
+    fn render(&mut self, _dt_ms: f64) {
+        let r = self.pixels.width().min(self.pixels.height()) as f64 / 8.;
+        let (x, y) = self.pointer;
+        let mut canvas = Canvas::new_entire(&mut self.pixels);
+        canvas.fill(BACKGROUND_COLOR);
+        let c = PixelPointF::from_float(0, x, 0, y);
+        canvas.fill_pixel_circle(c, r, CIRCLE_COLOR);
+    }
+
+    fn pixels(&self) -> &[Pixel] {
+        self.pixels.pixels()
+    }
+
+    fn event(&mut self, event: &Event) {
+        match *event {
+            Event::Resize { width, height } => {
+                self.pixels = HeapPixels2D::new(width, height, BACKGROUND_COLOR);
+            }
+            Event::PointerMove { x, y } => self.pointer = (x, y),
+            _ => (),
+        }
+    }
+}
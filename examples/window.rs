@@ -0,0 +1,62 @@
+use native::{run_window, WindowOptions};
+use olive_rs::{Canvas, Event, HeapPixels2D, Pixel, PixelPointF, Pixels2D, PointerButton, Render};
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 480;
+
+const BACKGROUND_COLOR: Pixel = Pixel::new(0x20, 0x20, 0x20, 0xff);
+const CIRCLE_COLOR: Pixel = Pixel::new(0, 0, 0xaa, 0x99);
+const PRESSED_COLOR: Pixel = Pixel::new(0xff, 0, 0, 0x99);
+
+fn main() {
+    let mut render = PulsingPointer {
+        pixels: HeapPixels2D::new(WIDTH, HEIGHT, BACKGROUND_COLOR),
+        pointer: (WIDTH as f64 / 2., HEIGHT as f64 / 2.),
+        pressed: false,
+        elapsed_s: 0.,
+    };
+    run_window(WIDTH, HEIGHT, &mut render, &WindowOptions::default()).unwrap();
+}
+
+/// A pulsing circle following the pointer, red while pressed
+struct PulsingPointer {
+    pixels: HeapPixels2D,
+    pointer: (f64, f64),
+    pressed: bool,
+    elapsed_s: f64,
+}
+
+impl Render for PulsingPointer {
+    fn render(&mut self, dt_ms: f64) {
+        self.elapsed_s += dt_ms * 0.001;
+        let r = 40. + 10. * (self.elapsed_s * 4.).sin();
+        let (x, y) = self.pointer;
+        let color = if self.pressed {
+            PRESSED_COLOR
+        } else {
+            CIRCLE_COLOR
+        };
+        let mut canvas = Canvas::new_entire(&mut self.pixels);
+        canvas.fill(BACKGROUND_COLOR);
+        canvas.fill_pixel_circle(PixelPointF::from_float(0, x, 0, y), r, color);
+    }
+
+    fn pixels(&self) -> &[Pixel] {
+        self.pixels.pixels()
+    }
+
+    fn event(&mut self, event: &Event) {
+        match *event {
+            Event::PointerMove { x, y } => self.pointer = (x, y),
+            Event::PointerDown {
+                button: PointerButton::Primary,
+                ..
+            } => self.pressed = true,
+            Event::PointerUp {
+                button: PointerButton::Primary,
+                ..
+            } => self.pressed = false,
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::Path,
+};
+
+use olive_rs::{Pixel, Render};
+
+use crate::create_file;
+
+/// How to step a [`Render`] implementation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationOptions {
+    /// Size of [`Render::pixels`]
+    pub width: usize,
+    pub height: usize,
+    pub frames: usize,
+    /// Time between two frames
+    pub dt_ms: f64,
+    /// How many times the animation is played, or 0 to play forever
+    pub plays: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dithering {
+    #[default]
+    None,
+    /// Ref: <https://en.wikipedia.org/wiki/Floyd%E2%80%93Steinberg_dithering>
+    FloydSteinberg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifOptions {
+    pub dithering: Dithering,
+    /// Size of the palette of each frame, from 2 to 256
+    pub max_colors: usize,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            dithering: Dithering::None,
+            max_colors: 256,
+        }
+    }
+}
+
+/// Step `render` for `options.frames` frames and pass each frame to `f`.
+///
+/// The first frame is rendered with a `dt_ms` of 0 just like in the `wasm` runner.
+pub(crate) fn for_each_frame<R, F>(
+    render: &mut R,
+    options: &AnimationOptions,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: Render,
+    F: FnMut(usize, &[Pixel]) -> io::Result<()>,
+{
+    for i in 0..options.frames {
+        let dt_ms = if i == 0 { 0. } else { options.dt_ms };
+        render.update(dt_ms);
+        render.render(dt_ms);
+        let pixels = render.pixels();
+        if pixels.len() != options.width * options.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the number of pixels does not match the size",
+            ));
+        }
+        f(i, pixels)?;
+    }
+    Ok(())
+}
+
+pub fn save_to_gif_file<P, R>(
+    render: &mut R,
+    options: &AnimationOptions,
+    gif_options: &GifOptions,
+    file_path: P,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    R: Render,
+{
+    let mut file = create_file(file_path)?;
+    save_to_gif_stream(render, options, gif_options, &mut file)?;
+    Ok(())
+}
+
+/// - Each frame has its own palette quantized by median cut.
+/// - Pixels with less than half alpha are transparent and the others are opaque.
+pub fn save_to_gif_stream<S, R>(
+    render: &mut R,
+    options: &AnimationOptions,
+    gif_options: &GifOptions,
+    stream: &mut S,
+) -> io::Result<()>
+where
+    S: Write,
+    R: Render,
+{
+    if !(2..=256).contains(&gif_options.max_colors) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "GIF palettes have 2 to 256 colors",
+        ));
+    }
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image is too large for GIF");
+    let width = u16::try_from(options.width).map_err(|_| too_large())?;
+    let height = u16::try_from(options.height).map_err(|_| too_large())?;
+
+    let mut encoder = gif::Encoder::new(stream, width, height, &[]).map_err(io::Error::other)?;
+    let repeat = match options.plays {
+        0 => gif::Repeat::Infinite,
+        n => gif::Repeat::Finite(n - 1),
+    };
+    encoder.set_repeat(repeat).map_err(io::Error::other)?;
+
+    for_each_frame(render, options, |i, pixels| {
+        let quantized = quantize(
+            pixels,
+            options.width,
+            gif_options.max_colors,
+            gif_options.dithering,
+        );
+        // Delays are in centiseconds, so round the timestamps instead of each delay to not drift
+        let t1 = (i as f64 * options.dt_ms / 10.).round();
+        let t2 = ((i + 1) as f64 * options.dt_ms / 10.).round();
+        let frame = gif::Frame {
+            delay: (t2 - t1).clamp(0., u16::MAX as f64) as u16,
+            dispose: gif::DisposalMethod::Background,
+            transparent: quantized.transparent,
+            width,
+            height,
+            palette: Some(quantized.palette),
+            buffer: quantized.indices.into(),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)
+    })
+}
+
+pub fn save_to_apng_file<P, R>(
+    render: &mut R,
+    options: &AnimationOptions,
+    file_path: P,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    R: Render,
+{
+    let mut file = create_file(file_path)?;
+    save_to_apng_stream(render, options, &mut file)?;
+    Ok(())
+}
+
+/// Lossless 8-bit RGBA frames
+pub fn save_to_apng_stream<S, R>(
+    render: &mut R,
+    options: &AnimationOptions,
+    stream: &mut S,
+) -> io::Result<()>
+where
+    S: Write,
+    R: Render,
+{
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image is too large for PNG");
+    let width = u32::try_from(options.width).map_err(|_| too_large())?;
+    let height = u32::try_from(options.height).map_err(|_| too_large())?;
+    let frames = u32::try_from(options.frames).map_err(|_| too_large())?;
+
+    let mut encoder = png::Encoder::new(stream, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames, options.plays as u32)?;
+    // In units of 0.1 ms if it fits
+    let (numerator, denominator) = if options.dt_ms * 10. <= u16::MAX as f64 {
+        ((options.dt_ms * 10.).round() as u16, 10_000)
+    } else {
+        (options.dt_ms.round().min(u16::MAX as f64) as u16, 1_000)
+    };
+    encoder.set_frame_delay(numerator, denominator)?;
+    let mut writer = encoder.write_header()?;
+
+    let mut png_pixels = Vec::new();
+    for_each_frame(render, options, |_, pixels| {
+        png_pixels.clear();
+        for pixel in pixels {
+            png_pixels.extend_from_slice(&[pixel.r(), pixel.g(), pixel.b(), pixel.a()]);
+        }
+        writer.write_image_data(&png_pixels)?;
+        Ok(())
+    })?;
+    writer.finish()?;
+    Ok(())
+}
+
+struct QuantizedFrame {
+    /// RGB triples
+    palette: Vec<u8>,
+    indices: Vec<u8>,
+    transparent: Option<u8>,
+}
+
+fn is_transparent(pixel: &Pixel) -> bool {
+    pixel.a() < 0x80
+}
+
+fn quantize(
+    pixels: &[Pixel],
+    width: usize,
+    max_colors: usize,
+    dithering: Dithering,
+) -> QuantizedFrame {
+    let has_transparency = pixels.iter().any(is_transparent);
+    // Reserve the last index for transparent pixels
+    let max_colors = if has_transparency {
+        max_colors - 1
+    } else {
+        max_colors
+    };
+
+    let mut histogram: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in pixels.iter().filter(|p| !is_transparent(p)) {
+        *histogram
+            .entry([pixel.r(), pixel.g(), pixel.b()])
+            .or_default() += 1;
+    }
+    let mut palette = median_cut(histogram.into_iter().collect(), max_colors);
+    if palette.is_empty() {
+        palette.push([0, 0, 0]);
+    }
+
+    let mut nearest_cache: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut nearest = |c: [u8; 3]| -> u8 {
+        *nearest_cache.entry(c).or_insert_with(|| {
+            let distance = |p: &[u8; 3]| -> i32 {
+                (0..3)
+                    .map(|i| (p[i] as i32 - c[i] as i32).pow(2))
+                    .sum::<i32>()
+            };
+            let (i, _) = palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| distance(p))
+                .unwrap();
+            i as u8
+        })
+    };
+
+    let transparent = has_transparency.then_some(palette.len() as u8);
+    let mut indices = Vec::with_capacity(pixels.len());
+    match dithering {
+        Dithering::None => {
+            for pixel in pixels {
+                let index = match transparent {
+                    Some(t) if is_transparent(pixel) => t,
+                    _ => nearest([pixel.r(), pixel.g(), pixel.b()]),
+                };
+                indices.push(index);
+            }
+        }
+        Dithering::FloydSteinberg => {
+            let mut errors = vec![[0f32; 3]; pixels.len()];
+            for (i, pixel) in pixels.iter().enumerate() {
+                if let (Some(t), true) = (transparent, is_transparent(pixel)) {
+                    indices.push(t);
+                    continue;
+                }
+                let rgb = [pixel.r(), pixel.g(), pixel.b()];
+                let wanted = (0..3).map(|c| rgb[c] as f32 + errors[i][c]);
+                let wanted = wanted.map(|v| v.round().clamp(0., 255.) as u8);
+                let wanted: Vec<u8> = wanted.collect();
+                let index = nearest([wanted[0], wanted[1], wanted[2]]);
+                indices.push(index);
+
+                let got = palette[index as usize];
+                let x = i % width;
+                let neighbors = [
+                    (x + 1 < width, i + 1, 7.),
+                    (x > 0, i + width - 1, 3.),
+                    (true, i + width, 5.),
+                    (x + 1 < width, i + width + 1, 1.),
+                ];
+                for c in 0..3 {
+                    let error = rgb[c] as f32 + errors[i][c] - got[c] as f32;
+                    for (valid, j, weight) in neighbors {
+                        if valid && j < pixels.len() {
+                            errors[j][c] += error * weight / 16.;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut palette = palette.concat();
+    if has_transparency {
+        palette.extend_from_slice(&[0, 0, 0]);
+    }
+    QuantizedFrame {
+        palette,
+        indices,
+        transparent,
+    }
+}
+
+/// Ref: <https://en.wikipedia.org/wiki/Median_cut>
+fn median_cut(colors: Vec<([u8; 3], usize)>, max_colors: usize) -> Vec<[u8; 3]> {
+    if colors.len() <= max_colors {
+        return colors.into_iter().map(|(c, _)| c).collect();
+    }
+
+    /// The channel with the widest range and the range
+    fn widest_channel(colors: &[([u8; 3], usize)]) -> (usize, u8) {
+        (0..3)
+            .map(|c| {
+                let min = colors.iter().map(|(p, _)| p[c]).min().unwrap();
+                let max = colors.iter().map(|(p, _)| p[c]).max().unwrap();
+                (c, max - min)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap()
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Split the box with the widest range weighted by its population
+        let Some((i, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .max_by_key(|(_, b)| {
+                let population = b.iter().map(|(_, n)| n).sum::<usize>();
+                widest_channel(b).1 as usize * population
+            })
+        else {
+            break;
+        };
+        let mut b = boxes.swap_remove(i);
+        let (channel, _) = widest_channel(&b);
+        b.sort_by_key(|(p, _)| p[channel]);
+
+        // Split at the weighted median but keep both halves non-empty
+        let population = b.iter().map(|(_, n)| n).sum::<usize>();
+        let mut acc = 0;
+        let mut split = 1;
+        for (j, (_, n)) in b.iter().enumerate() {
+            acc += n;
+            if acc * 2 >= population {
+                split = j + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, b.len() - 1);
+        let upper = b.split_off(split);
+        boxes.push(b);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let population = b.iter().map(|(_, n)| n).sum::<usize>();
+            let mut sum = [0usize; 3];
+            for (p, n) in b {
+                for c in 0..3 {
+                    sum[c] += p[c] as usize * n;
+                }
+            }
+            sum.map(|s| ((s + population / 2) / population) as u8)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A horizontal gradient shifting by one pixel each frame, with a transparent first column
+    struct Gradient {
+        frame: usize,
+        pixels: Vec<Pixel>,
+    }
+
+    const W: usize = 16;
+    const H: usize = 4;
+
+    impl Render for Gradient {
+        fn render(&mut self, dt_ms: f64) {
+            if dt_ms > 0. {
+                self.frame += 1;
+            }
+            self.pixels = (0..W * H)
+                .map(|i| {
+                    let x = i % W;
+                    let v = ((x + self.frame) * 16) as u8;
+                    let a = if x == 0 { 0 } else { 0xff };
+                    Pixel::new(v, 0xff - v, 0x80, a)
+                })
+                .collect();
+        }
+
+        fn pixels(&self) -> &[Pixel] {
+            &self.pixels
+        }
+    }
+
+    fn options() -> AnimationOptions {
+        AnimationOptions {
+            width: W,
+            height: H,
+            frames: 3,
+            dt_ms: 1000. / 30.,
+            plays: 0,
+        }
+    }
+
+    #[test]
+    fn gif() {
+        for dithering in [Dithering::None, Dithering::FloydSteinberg] {
+            let mut render = Gradient {
+                frame: 0,
+                pixels: Vec::new(),
+            };
+            let gif_options = GifOptions {
+                dithering,
+                max_colors: 8,
+            };
+            let mut bytes = Vec::new();
+            save_to_gif_stream(&mut render, &options(), &gif_options, &mut bytes).unwrap();
+
+            let mut decoder = gif::DecodeOptions::new();
+            decoder.set_color_output(gif::ColorOutput::Indexed);
+            let mut decoder = decoder.read_info(bytes.as_slice()).unwrap();
+            let mut delays = Vec::new();
+            while let Some(frame) = decoder.read_next_frame().unwrap() {
+                let palette = frame.palette.as_ref().unwrap();
+                assert!(palette.len() <= 8 * 3);
+                assert_eq!(frame.transparent, Some(frame.buffer[0]));
+                assert_ne!(frame.transparent, Some(frame.buffer[1]));
+                delays.push(frame.delay);
+            }
+            assert_eq!(delays, [3, 4, 3]);
+        }
+    }
+
+    #[test]
+    fn apng() {
+        let mut render = Gradient {
+            frame: 0,
+            pixels: Vec::new(),
+        };
+        let mut bytes = Vec::new();
+        save_to_apng_stream(&mut render, &options(), &mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let animation = reader.info().animation_control().unwrap();
+        assert_eq!(animation.num_frames, 3);
+        assert_eq!(animation.num_plays, 0);
+        let mut buf = vec![0; reader.output_buffer_size()];
+        for frame in 0..3 {
+            reader.next_frame(&mut buf).unwrap();
+            // Second pixel
+            assert_eq!(buf[4], ((1 + frame) * 16) as u8);
+        }
+    }
+
+    #[test]
+    fn median_cut_keeps_few_colors() {
+        let colors = vec![([1, 2, 3], 5), ([4, 5, 6], 1)];
+        assert_eq!(median_cut(colors, 4), [[1, 2, 3], [4, 5, 6]]);
+
+        let colors = (0..=255).map(|v| ([v, v, v], 1)).collect();
+        let palette = median_cut(colors, 4);
+        assert_eq!(palette.len(), 4);
+    }
+}
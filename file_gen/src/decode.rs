@@ -0,0 +1,628 @@
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+
+use olive_rs::{HeapPixels2D, Pixel};
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    /// The data does not follow the format
+    Malformed(&'static str),
+    /// The data is valid but uses a feature that is not supported
+    Unsupported(&'static str),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Io(e) => write!(f, "{e}"),
+            DecodeError::Png(e) => write!(f, "{e}"),
+            DecodeError::Malformed(reason) => write!(f, "malformed image: {reason}"),
+            DecodeError::Unsupported(reason) => write!(f, "unsupported image: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Io(e) => Some(e),
+            DecodeError::Png(e) => Some(e),
+            DecodeError::Malformed(_) | DecodeError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> Self {
+        DecodeError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for DecodeError {
+    fn from(e: png::DecodingError) -> Self {
+        match e {
+            png::DecodingError::IoError(e) => DecodeError::Io(e),
+            e => DecodeError::Png(e),
+        }
+    }
+}
+
+fn open_file<P>(file_path: P) -> io::Result<io::BufReader<std::fs::File>>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::open(file_path)?;
+    Ok(io::BufReader::new(file))
+}
+
+/// Reject sizes that cannot be backed by `available` units of data
+///
+/// It returns the product of `dims`.
+fn check_size(dims: &[usize], available: usize) -> Result<usize, DecodeError> {
+    let len = dims
+        .iter()
+        .try_fold(1usize, |len, d| len.checked_mul(*d))
+        .ok_or(DecodeError::Malformed("image is too large"))?;
+    if len > available {
+        return Err(DecodeError::Malformed("not enough pixel data"));
+    }
+    Ok(len)
+}
+
+pub fn load_from_png_file<P>(file_path: P) -> Result<HeapPixels2D, DecodeError>
+where
+    P: AsRef<Path>,
+{
+    let mut file = open_file(file_path)?;
+    load_from_png_stream(&mut file)
+}
+
+/// All color types and bit depths are converted to 8-bit RGBA.
+pub fn load_from_png_stream<S>(stream: &mut S) -> Result<HeapPixels2D, DecodeError>
+where
+    S: Read,
+{
+    let mut decoder = png::Decoder::new(stream);
+    // Expand palettes, low bit depths and transparency chunks, and strip 16-bit samples to 8 bits
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let width = info.width as usize;
+    let height = info.height as usize;
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(DecodeError::Unsupported("bit depth after normalization"));
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buf.chunks(info.line_size).take(height) {
+        match info.color_type {
+            png::ColorType::Grayscale => {
+                pixels.extend(row[..width].iter().map(|&v| Pixel::new(v, v, v, u8::MAX)));
+            }
+            png::ColorType::GrayscaleAlpha => {
+                pixels.extend(
+                    row[..width * 2]
+                        .chunks(2)
+                        .map(|p| Pixel::new(p[0], p[0], p[0], p[1])),
+                );
+            }
+            png::ColorType::Rgb => {
+                pixels.extend(
+                    row[..width * 3]
+                        .chunks(3)
+                        .map(|p| Pixel::new(p[0], p[1], p[2], u8::MAX)),
+                );
+            }
+            png::ColorType::Rgba => {
+                pixels.extend(
+                    row[..width * 4]
+                        .chunks(4)
+                        .map(|p| Pixel::new(p[0], p[1], p[2], p[3])),
+                );
+            }
+            png::ColorType::Indexed => {
+                return Err(DecodeError::Unsupported(
+                    "indexed color after normalization",
+                ));
+            }
+        }
+    }
+    Ok(HeapPixels2D::from_pixels(width, height, pixels))
+}
+
+pub fn load_from_ppm_file<P>(file_path: P) -> Result<HeapPixels2D, DecodeError>
+where
+    P: AsRef<Path>,
+{
+    let mut file = open_file(file_path)?;
+    load_from_ppm_stream(&mut file)
+}
+
+/// Read any of the Netpbm formats `P1` to `P6`: PBM, PGM and PPM in either ASCII or binary.
+///
+/// Samples are scaled to 8 bits and all pixels are opaque.
+pub fn load_from_ppm_stream<S>(stream: &mut S) -> Result<HeapPixels2D, DecodeError>
+where
+    S: Read,
+{
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data)?;
+    let mut header = NetpbmReader { data: &data, i: 0 };
+
+    let magic = header.token()?;
+    let magic = match magic {
+        [b'P', n @ b'1'..=b'6'] => n - b'0',
+        _ => return Err(DecodeError::Malformed("not a Netpbm image")),
+    };
+    let width = header.number()?;
+    let height = header.number()?;
+    let is_bitmap = magic == 1 || magic == 4;
+    let max_value = if is_bitmap { 1 } else { header.number()? };
+    if !(1..=u16::MAX as usize).contains(&max_value) {
+        return Err(DecodeError::Malformed("maximum value is out of range"));
+    }
+    let channels = match magic {
+        1 | 2 | 4 | 5 => 1,
+        _ => 3,
+    };
+    let scale = |v: usize| -> Result<u8, DecodeError> {
+        if v > max_value {
+            return Err(DecodeError::Malformed(
+                "sample is larger than the maximum value",
+            ));
+        }
+        Ok(((v * u8::MAX as usize + max_value / 2) / max_value) as u8)
+    };
+    let to_pixel = |samples: &[u8]| match samples {
+        [v] => Pixel::new(*v, *v, *v, u8::MAX),
+        [r, g, b] => Pixel::new(*r, *g, *b, u8::MAX),
+        _ => unreachable!(),
+    };
+
+    let mut samples = Vec::new();
+    match magic {
+        // ASCII bits where 1 is black
+        1 => {
+            let len = check_size(&[width, height], data.len())?;
+            while samples.len() < len {
+                let bit = header.bit()?;
+                samples.push(if bit { 0 } else { u8::MAX });
+            }
+        }
+        2 | 3 => {
+            let len = check_size(&[width, channels, height], data.len())?;
+            while samples.len() < len {
+                samples.push(scale(header.number()?)?);
+            }
+        }
+        // Binary bits packed in rows padded to bytes
+        4 => {
+            let raster = header.raster()?;
+            let row_len = width.div_ceil(8);
+            check_size(&[row_len, height], raster.len())?;
+            for row in raster.chunks(row_len.max(1)).take(height) {
+                for x in 0..width {
+                    let bit = row[x / 8] & (0x80 >> (x % 8)) != 0;
+                    samples.push(if bit { 0 } else { u8::MAX });
+                }
+            }
+        }
+        _ => {
+            let raster = header.raster()?;
+            let bytes_per_sample = if max_value > u8::MAX as usize { 2 } else { 1 };
+            let len = check_size(&[width, channels, height], raster.len() / bytes_per_sample)?;
+            for sample in raster.chunks(bytes_per_sample).take(len) {
+                let v = match sample {
+                    [v] => *v as usize,
+                    [hi, lo] => u16::from_be_bytes([*hi, *lo]) as usize,
+                    _ => unreachable!(),
+                };
+                samples.push(scale(v)?);
+            }
+        }
+    }
+
+    let pixels = samples.chunks(channels).map(to_pixel).collect();
+    Ok(HeapPixels2D::from_pixels(width, height, pixels))
+}
+
+struct NetpbmReader<'a> {
+    data: &'a [u8],
+    i: usize,
+}
+
+impl<'a> NetpbmReader<'a> {
+    /// Skip whitespace and comments
+    fn skip_space(&mut self) {
+        while let Some(&c) = self.data.get(self.i) {
+            if c == b'#' {
+                while self.data.get(self.i).is_some_and(|&c| c != b'\n') {
+                    self.i += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.i += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn token(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.skip_space();
+        let start = self.i;
+        while self
+            .data
+            .get(self.i)
+            .is_some_and(|c| !c.is_ascii_whitespace() && *c != b'#')
+        {
+            self.i += 1;
+        }
+        if start == self.i {
+            return Err(DecodeError::Malformed("unexpected end of data"));
+        }
+        Ok(&self.data[start..self.i])
+    }
+
+    pub fn number(&mut self) -> Result<usize, DecodeError> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or(DecodeError::Malformed("expected a number"))
+    }
+
+    /// Bits in `P1` need not be separated by whitespace
+    pub fn bit(&mut self) -> Result<bool, DecodeError> {
+        self.skip_space();
+        let bit = match self.data.get(self.i) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            Some(_) => return Err(DecodeError::Malformed("expected a bit")),
+            None => return Err(DecodeError::Malformed("unexpected end of data")),
+        };
+        self.i += 1;
+        Ok(bit)
+    }
+
+    /// Binary data after the single whitespace that ends the header
+    pub fn raster(&mut self) -> Result<&'a [u8], DecodeError> {
+        match self.data.get(self.i) {
+            Some(c) if c.is_ascii_whitespace() => Ok(&self.data[self.i + 1..]),
+            _ => Err(DecodeError::Malformed(
+                "expected whitespace after the header",
+            )),
+        }
+    }
+}
+
+pub fn load_from_bmp_file<P>(file_path: P) -> Result<HeapPixels2D, DecodeError>
+where
+    P: AsRef<Path>,
+{
+    let mut file = open_file(file_path)?;
+    load_from_bmp_stream(&mut file)
+}
+
+/// Read an uncompressed BMP with 1, 4, 8, 16, 24 or 32 bits per pixel.
+///
+/// - Bit fields are supported, including an alpha mask.
+/// - A 32-bit image without an alpha mask whose alpha bytes are all zero is read as opaque.
+pub fn load_from_bmp_stream<S>(stream: &mut S) -> Result<HeapPixels2D, DecodeError>
+where
+    S: Read,
+{
+    const BI_RGB: u32 = 0;
+    const BI_BITFIELDS: u32 = 3;
+    const BI_ALPHABITFIELDS: u32 = 6;
+    const FILE_HEADER_SIZE: usize = 14;
+    const CORE_HEADER_SIZE: usize = 12;
+    const INFO_HEADER_SIZE: usize = 40;
+
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data)?;
+    let bytes = BmpBytes { data: &data };
+
+    if bytes.get(0, 2)? != b"BM" {
+        return Err(DecodeError::Malformed("not a BMP image"));
+    }
+    let pixel_offset = bytes.u32(10)? as usize;
+    let header_size = bytes.u32(FILE_HEADER_SIZE)? as usize;
+    let (width, height, bits_per_pixel, compression, colors_used) = match header_size {
+        CORE_HEADER_SIZE => {
+            let width = bytes.u16(FILE_HEADER_SIZE + 4)? as i64;
+            let height = bytes.u16(FILE_HEADER_SIZE + 6)? as i64;
+            let bits_per_pixel = bytes.u16(FILE_HEADER_SIZE + 10)?;
+            (width, height, bits_per_pixel, BI_RGB, 0)
+        }
+        size if size >= INFO_HEADER_SIZE => {
+            let width = bytes.u32(FILE_HEADER_SIZE + 4)? as i32 as i64;
+            let height = bytes.u32(FILE_HEADER_SIZE + 8)? as i32 as i64;
+            let bits_per_pixel = bytes.u16(FILE_HEADER_SIZE + 14)?;
+            let compression = bytes.u32(FILE_HEADER_SIZE + 16)?;
+            let colors_used = bytes.u32(FILE_HEADER_SIZE + 32)? as usize;
+            (width, height, bits_per_pixel, compression, colors_used)
+        }
+        _ => return Err(DecodeError::Unsupported("BMP header size")),
+    };
+    if width < 0 {
+        return Err(DecodeError::Malformed("negative width"));
+    }
+    // Rows are stored from bottom to top unless the height is negative
+    let top_down = height < 0;
+    let width = width as usize;
+    let height = height.unsigned_abs() as usize;
+
+    // Bit fields as `[r, g, b, a]`
+    let masks = match (compression, bits_per_pixel) {
+        (BI_RGB, 16) => Some([0x7c00, 0x03e0, 0x001f, 0]),
+        (BI_RGB, 32) => Some([0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0xff00_0000]),
+        (BI_RGB, _) => None,
+        (BI_BITFIELDS | BI_ALPHABITFIELDS, 16 | 32) => {
+            // The masks follow a BITMAPINFOHEADER, or are part of the larger headers
+            let offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+            let has_alpha = compression == BI_ALPHABITFIELDS || header_size >= 56;
+            let a = if has_alpha {
+                bytes.u32(offset + 12)?
+            } else {
+                0
+            };
+            Some([
+                bytes.u32(offset)?,
+                bytes.u32(offset + 4)?,
+                bytes.u32(offset + 8)?,
+                a,
+            ])
+        }
+        _ => return Err(DecodeError::Unsupported("BMP compression")),
+    };
+
+    let palette = match bits_per_pixel {
+        1 | 4 | 8 => {
+            let entry_size = if header_size == CORE_HEADER_SIZE {
+                3
+            } else {
+                4
+            };
+            let count = match colors_used {
+                0 => 1 << bits_per_pixel,
+                n => n.min(1 << bits_per_pixel),
+            };
+            let start = FILE_HEADER_SIZE + header_size;
+            let palette = bytes.get(start, count * entry_size)?;
+            palette
+                .chunks(entry_size)
+                .map(|c| Pixel::new(c[2], c[1], c[0], u8::MAX))
+                .collect::<Vec<_>>()
+        }
+        16 | 24 | 32 => Vec::new(),
+        _ => return Err(DecodeError::Unsupported("BMP bits per pixel")),
+    };
+
+    let bits_per_pixel = bits_per_pixel as usize;
+    let row_size = width
+        .checked_mul(bits_per_pixel)
+        .ok_or(DecodeError::Malformed("image is too large"))?
+        .div_ceil(32)
+        * 4;
+    let raster_len = row_size
+        .checked_mul(height)
+        .ok_or(DecodeError::Malformed("image is too large"))?;
+    let raster = bytes.get(pixel_offset, raster_len)?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row_y = if top_down { y } else { height - 1 - y };
+        let row = &raster[row_y * row_size..(row_y + 1) * row_size];
+        for x in 0..width {
+            let pixel = match bits_per_pixel {
+                1 | 4 | 8 => {
+                    let bit = x * bits_per_pixel;
+                    let shift = 8 - bits_per_pixel - bit % 8;
+                    let index = (row[bit / 8] >> shift) as usize & ((1 << bits_per_pixel) - 1);
+                    *palette
+                        .get(index)
+                        .ok_or(DecodeError::Malformed("palette index is out of range"))?
+                }
+                24 => {
+                    let p = &row[x * 3..x * 3 + 3];
+                    Pixel::new(p[2], p[1], p[0], u8::MAX)
+                }
+                _ => {
+                    let bytes_per_pixel = bits_per_pixel / 8;
+                    let p = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+                    let mut value = 0;
+                    for (i, b) in p.iter().enumerate() {
+                        value |= (*b as u32) << (8 * i);
+                    }
+                    let [r, g, b, a] = masks.unwrap().map(|mask| extract_bits(value, mask));
+                    Pixel::new(
+                        r.unwrap_or(0),
+                        g.unwrap_or(0),
+                        b.unwrap_or(0),
+                        a.unwrap_or(u8::MAX),
+                    )
+                }
+            };
+            pixels.push(pixel);
+        }
+    }
+
+    // A BI_RGB 32-bit image usually leaves the alpha bytes unused
+    if compression == BI_RGB && bits_per_pixel == 32 && pixels.iter().all(|p| p.a() == 0) {
+        for p in &mut pixels {
+            *p = Pixel::new(p.r(), p.g(), p.b(), u8::MAX);
+        }
+    }
+    Ok(HeapPixels2D::from_pixels(width, height, pixels))
+}
+
+/// Scale the bits selected by `mask` to 8 bits
+///
+/// It returns [`None`] if the mask is empty.
+fn extract_bits(value: u32, mask: u32) -> Option<u8> {
+    if mask == 0 {
+        return None;
+    }
+    let shift = mask.trailing_zeros();
+    let max = mask >> shift;
+    let v = (value & mask) >> shift;
+    Some(((v as u64 * u8::MAX as u64 + max as u64 / 2) / max as u64) as u8)
+}
+
+struct BmpBytes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> BmpBytes<'a> {
+    pub fn get(&self, offset: usize, len: usize) -> Result<&'a [u8], DecodeError> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(DecodeError::Malformed("unexpected end of data"))
+    }
+
+    pub fn u16(&self, offset: usize) -> Result<u16, DecodeError> {
+        let bytes = self.get(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&self, offset: usize) -> Result<u32, DecodeError> {
+        let bytes = self.get(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use olive_rs::Pixels2D;
+
+    use crate::{save_to_png_stream, save_to_ppm_stream};
+
+    use super::*;
+
+    fn test_pixels() -> HeapPixels2D {
+        let pixels = (0..6u8)
+            .map(|i| Pixel::new(i * 40, 255 - i * 40, i, 0xff))
+            .collect();
+        HeapPixels2D::from_pixels(3, 2, pixels)
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut pixels = test_pixels();
+        pixels.pixels_mut()[1] = Pixel::new(1, 2, 3, 4);
+        let mut bytes = Vec::new();
+        save_to_png_stream(&pixels, &mut bytes).unwrap();
+        let decoded = load_from_png_stream(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn png_16_bit_grayscale() {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0x12, 0x34, 0xff, 0xff]).unwrap();
+        writer.finish().unwrap();
+
+        let decoded = load_from_png_stream(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            decoded.pixels(),
+            [
+                Pixel::new(0x12, 0x12, 0x12, 0xff),
+                Pixel::new(0xff, 0xff, 0xff, 0xff)
+            ]
+        );
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let pixels = test_pixels();
+        let mut bytes = Vec::new();
+        save_to_ppm_stream(&pixels, &mut bytes).unwrap();
+        let decoded = load_from_ppm_stream(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn ascii_netpbm() {
+        let gray = |v| Pixel::new(v, v, v, 0xff);
+
+        let pbm = b"P1\n# comment\n3 1\n101";
+        let decoded = load_from_ppm_stream(&mut pbm.as_slice()).unwrap();
+        assert_eq!(decoded.pixels(), [gray(0), gray(0xff), gray(0)]);
+
+        let pgm = b"P2 2 1 4 0 4";
+        let decoded = load_from_ppm_stream(&mut pgm.as_slice()).unwrap();
+        assert_eq!(decoded.pixels(), [gray(0), gray(0xff)]);
+
+        let ppm = b"P3 1 1 255 1 2 3";
+        let decoded = load_from_ppm_stream(&mut ppm.as_slice()).unwrap();
+        assert_eq!(decoded.pixels(), [Pixel::new(1, 2, 3, 0xff)]);
+    }
+
+    #[test]
+    fn binary_bitmap() {
+        let pbm = b"P4 9 1\n\x80\x80";
+        let decoded = load_from_ppm_stream(&mut pbm.as_slice()).unwrap();
+        let mut expected = vec![Pixel::new(0xff, 0xff, 0xff, 0xff); 9];
+        expected[0] = Pixel::new(0, 0, 0, 0xff);
+        expected[8] = Pixel::new(0, 0, 0, 0xff);
+        assert_eq!(decoded.pixels(), expected);
+    }
+
+    #[test]
+    fn malformed_netpbm() {
+        for data in [
+            &b"P7 1 1 255 0"[..],
+            b"P3 1 1 255 1 2",
+            b"P3 1 1 255 1 2 300",
+            b"P6 100000 100000 255\n",
+            b"P6 1 1 255\n\x00",
+        ] {
+            let result = load_from_ppm_stream(&mut &data[..]);
+            assert!(matches!(result, Err(DecodeError::Malformed(_))), "{data:?}");
+        }
+    }
+
+    #[test]
+    fn bmp() {
+        // 2x2, 24 bits per pixel, bottom-up
+        let mut bmp = Vec::new();
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&(14 + 40 + 16u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&(14 + 40u32).to_le_bytes());
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&2i32.to_le_bytes());
+        bmp.extend_from_slice(&2i32.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        bmp.extend_from_slice(&[0; 24]);
+        // Bottom row: blue, green
+        bmp.extend_from_slice(&[0xff, 0, 0, 0, 0xff, 0, 0, 0]);
+        // Top row: red, white
+        bmp.extend_from_slice(&[0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0]);
+
+        let decoded = load_from_bmp_stream(&mut bmp.as_slice()).unwrap();
+        assert_eq!(
+            decoded.pixels(),
+            [
+                Pixel::new(0xff, 0, 0, 0xff),
+                Pixel::new(0xff, 0xff, 0xff, 0xff),
+                Pixel::new(0, 0, 0xff, 0xff),
+                Pixel::new(0, 0xff, 0, 0xff),
+            ]
+        );
+
+        let truncated = load_from_bmp_stream(&mut &bmp[..bmp.len() - 1]);
+        assert!(matches!(truncated, Err(DecodeError::Malformed(_))));
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use olive_rs::{DisplayList, Pixel, Pixels2D};
+
+use crate::create_file;
+
+fn too_large(format: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("image is too large for {format}"),
+    )
+}
+
+pub fn save_to_bmp_file<P, CP>(pixels: &CP, file_path: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    CP: Pixels2D,
+{
+    let mut file = create_file(file_path)?;
+    save_to_bmp_stream(pixels, &mut file)?;
+    Ok(())
+}
+
+/// 32-bit BGRA with a `BITMAPV4HEADER` so that the alpha channel is kept
+pub fn save_to_bmp_stream<S, CP>(pixels: &CP, stream: &mut S) -> io::Result<()>
+where
+    S: Write,
+    CP: Pixels2D,
+{
+    const FILE_HEADER_SIZE: u32 = 14;
+    const V4_HEADER_SIZE: u32 = 108;
+    const BI_BITFIELDS: u32 = 3;
+    const LCS_SRGB: u32 = 0x7352_4742;
+
+    let width = i32::try_from(pixels.width()).map_err(|_| too_large("BMP"))?;
+    let height = i32::try_from(pixels.height()).map_err(|_| too_large("BMP"))?;
+    let image_size = (width as u32)
+        .checked_mul(height as u32)
+        .and_then(|n| n.checked_mul(4))
+        .ok_or_else(|| too_large("BMP"))?;
+    let pixel_offset = FILE_HEADER_SIZE + V4_HEADER_SIZE;
+    let file_size = pixel_offset
+        .checked_add(image_size)
+        .ok_or_else(|| too_large("BMP"))?;
+
+    // BITMAPFILEHEADER
+    stream.write_all(b"BM")?;
+    stream.write_all(&file_size.to_le_bytes())?;
+    stream.write_all(&[0; 4])?;
+    stream.write_all(&pixel_offset.to_le_bytes())?;
+
+    // BITMAPV4HEADER
+    stream.write_all(&V4_HEADER_SIZE.to_le_bytes())?;
+    stream.write_all(&width.to_le_bytes())?;
+    // Positive height for rows from bottom to top, which legacy readers expect
+    stream.write_all(&height.to_le_bytes())?;
+    stream.write_all(&1u16.to_le_bytes())?;
+    stream.write_all(&32u16.to_le_bytes())?;
+    stream.write_all(&BI_BITFIELDS.to_le_bytes())?;
+    stream.write_all(&image_size.to_le_bytes())?;
+    // 72 DPI in pixels per meter
+    stream.write_all(&2835u32.to_le_bytes())?;
+    stream.write_all(&2835u32.to_le_bytes())?;
+    // Palette sizes
+    stream.write_all(&[0; 8])?;
+    // Red, green, blue and alpha masks
+    for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+        stream.write_all(&mask.to_le_bytes())?;
+    }
+    stream.write_all(&LCS_SRGB.to_le_bytes())?;
+    // Endpoints and gamma are unused for sRGB
+    stream.write_all(&[0; 36 + 12])?;
+
+    let w = pixels.width();
+    for row in pixels.pixels().chunks(w.max(1)).rev() {
+        for pixel in row {
+            stream.write_all(&[pixel.b(), pixel.g(), pixel.r(), pixel.a()])?;
+        }
+    }
+    Ok(())
+}
+
+pub fn save_to_tga_file<P, CP>(pixels: &CP, file_path: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    CP: Pixels2D,
+{
+    let mut file = create_file(file_path)?;
+    save_to_tga_stream(pixels, &mut file)?;
+    Ok(())
+}
+
+/// Uncompressed 32-bit true-color with 8 alpha bits and the origin at the top-left corner
+pub fn save_to_tga_stream<S, CP>(pixels: &CP, stream: &mut S) -> io::Result<()>
+where
+    S: Write,
+    CP: Pixels2D,
+{
+    const UNCOMPRESSED_TRUE_COLOR: u8 = 2;
+    const ALPHA_BITS: u8 = 8;
+    const TOP_LEFT_ORIGIN: u8 = 0x20;
+
+    let width = u16::try_from(pixels.width()).map_err(|_| too_large("TGA"))?;
+    let height = u16::try_from(pixels.height()).map_err(|_| too_large("TGA"))?;
+
+    // ID length, color map type and image type
+    stream.write_all(&[0, 0, UNCOMPRESSED_TRUE_COLOR])?;
+    // Color map specification
+    stream.write_all(&[0; 5])?;
+    // X and y origin
+    stream.write_all(&[0; 4])?;
+    stream.write_all(&width.to_le_bytes())?;
+    stream.write_all(&height.to_le_bytes())?;
+    stream.write_all(&[32, ALPHA_BITS | TOP_LEFT_ORIGIN])?;
+
+    for pixel in pixels.pixels() {
+        stream.write_all(&[pixel.b(), pixel.g(), pixel.r(), pixel.a()])?;
+    }
+
+    // TGA 2.0 footer without extension and developer areas
+    stream.write_all(&[0; 8])?;
+    stream.write_all(b"TRUEVISION-XFILE.\0")?;
+    Ok(())
+}
+
+pub fn save_to_qoi_file<P, CP>(pixels: &CP, file_path: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    CP: Pixels2D,
+{
+    let mut file = create_file(file_path)?;
+    save_to_qoi_stream(pixels, &mut file)?;
+    Ok(())
+}
+
+/// Ref: <https://qoiformat.org/qoi-specification.pdf>
+pub fn save_to_qoi_stream<S, CP>(pixels: &CP, stream: &mut S) -> io::Result<()>
+where
+    S: Write,
+    CP: Pixels2D,
+{
+    const QOI_OP_INDEX: u8 = 0x00;
+    const QOI_OP_DIFF: u8 = 0x40;
+    const QOI_OP_LUMA: u8 = 0x80;
+    const QOI_OP_RUN: u8 = 0xc0;
+    const QOI_OP_RGB: u8 = 0xfe;
+    const QOI_OP_RGBA: u8 = 0xff;
+    const MAX_RUN: u8 = 62;
+    const RGBA: u8 = 4;
+    const SRGB: u8 = 0;
+
+    fn hash(p: Pixel) -> usize {
+        (p.r() as usize * 3 + p.g() as usize * 5 + p.b() as usize * 7 + p.a() as usize * 11) % 64
+    }
+
+    let width = u32::try_from(pixels.width()).map_err(|_| too_large("QOI"))?;
+    let height = u32::try_from(pixels.height()).map_err(|_| too_large("QOI"))?;
+    stream.write_all(b"qoif")?;
+    stream.write_all(&width.to_be_bytes())?;
+    stream.write_all(&height.to_be_bytes())?;
+    stream.write_all(&[RGBA, SRGB])?;
+
+    let mut index = [Pixel::new(0, 0, 0, 0); 64];
+    let mut prev = Pixel::new(0, 0, 0, u8::MAX);
+    let mut run: u8 = 0;
+    let mut bytes = Vec::new();
+    let len = pixels.pixels().len();
+    for (i, &pixel) in pixels.pixels().iter().enumerate() {
+        if pixel == prev {
+            run += 1;
+            if run == MAX_RUN || i + 1 == len {
+                bytes.push(QOI_OP_RUN | (run - 1));
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            bytes.push(QOI_OP_RUN | (run - 1));
+            run = 0;
+        }
+
+        let h = hash(pixel);
+        if index[h] == pixel {
+            bytes.push(QOI_OP_INDEX | h as u8);
+        } else {
+            index[h] = pixel;
+            if pixel.a() == prev.a() {
+                let dr = pixel.r().wrapping_sub(prev.r()) as i8;
+                let dg = pixel.g().wrapping_sub(prev.g()) as i8;
+                let db = pixel.b().wrapping_sub(prev.b()) as i8;
+                let dr_dg = dr.wrapping_sub(dg);
+                let db_dg = db.wrapping_sub(dg);
+                let small = -2..=1;
+                if small.contains(&dr) && small.contains(&dg) && small.contains(&db) {
+                    let diff = ((dr + 2) << 4 | (dg + 2) << 2 | (db + 2)) as u8;
+                    bytes.push(QOI_OP_DIFF | diff);
+                } else if (-32..=31).contains(&dg)
+                    && (-8..=7).contains(&dr_dg)
+                    && (-8..=7).contains(&db_dg)
+                {
+                    bytes.push(QOI_OP_LUMA | (dg + 32) as u8);
+                    bytes.push(((dr_dg + 8) << 4 | (db_dg + 8)) as u8);
+                } else {
+                    bytes.extend_from_slice(&[QOI_OP_RGB, pixel.r(), pixel.g(), pixel.b()]);
+                }
+            } else {
+                bytes.extend_from_slice(&[QOI_OP_RGBA, pixel.r(), pixel.g(), pixel.b(), pixel.a()]);
+            }
+        }
+        prev = pixel;
+    }
+    stream.write_all(&bytes)?;
+
+    // End marker
+    stream.write_all(&[0, 0, 0, 0, 0, 0, 0, 1])?;
+    Ok(())
+}
+
+pub fn save_to_svg_file<P>(display_list: &DisplayList<'_>, file_path: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let mut file = create_file(file_path)?;
+    save_to_svg_stream(display_list, &mut file)?;
+    Ok(())
+}
+
+pub fn save_to_svg_stream<S>(display_list: &DisplayList<'_>, stream: &mut S) -> io::Result<()>
+where
+    S: Write,
+{
+    stream.write_all(display_list.to_svg().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use olive_rs::HeapPixels2D;
+
+    use crate::load_from_bmp_stream;
+
+    use super::*;
+
+    fn test_pixels() -> HeapPixels2D {
+        let pixels = vec![
+            Pixel::new(0xff, 0, 0, 0xff),
+            Pixel::new(0, 0xff, 0, 0x80),
+            Pixel::new(0, 0, 0xff, 0),
+            Pixel::new(1, 2, 3, 4),
+        ];
+        HeapPixels2D::from_pixels(2, 2, pixels)
+    }
+
+    #[test]
+    fn bmp_round_trip() {
+        let pixels = test_pixels();
+        let mut bytes = Vec::new();
+        save_to_bmp_stream(&pixels, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 14 + 108 + 4 * 4);
+        let decoded = load_from_bmp_stream(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn tga() {
+        let pixels = test_pixels();
+        let mut bytes = Vec::new();
+        save_to_tga_stream(&pixels, &mut bytes).unwrap();
+        assert_eq!(bytes[2], 2);
+        assert_eq!(&bytes[12..18], [2, 0, 2, 0, 32, 0x28]);
+        assert_eq!(&bytes[18..22], [0, 0, 0xff, 0xff]);
+        assert_eq!(&bytes[30..34], [3, 2, 1, 4]);
+        assert!(bytes.ends_with(b"TRUEVISION-XFILE.\0"));
+    }
+
+    #[test]
+    fn qoi() {
+        let black = Pixel::new(0, 0, 0, 0xff);
+        let pixels = vec![
+            black,
+            black,
+            Pixel::new(1, 0, 0xff, 0xff),
+            Pixel::new(11, 10, 9, 0xff),
+            Pixel::new(11, 10, 9, 0x80),
+            Pixel::new(1, 0, 0xff, 0xff),
+        ];
+        let pixels = HeapPixels2D::from_pixels(3, 2, pixels);
+        let mut bytes = Vec::new();
+        save_to_qoi_stream(&pixels, &mut bytes).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(b"qoif");
+        expected.extend_from_slice(&[0, 0, 0, 3, 0, 0, 0, 2, 4, 0]);
+        // Run of 2
+        expected.push(0xc1);
+        // Diff of (1, 0, -1)
+        expected.push(0x40 | 3 << 4 | 2 << 2 | 1);
+        // Luma of dg = 10, dr - dg = 0, db - dg = 0
+        expected.extend_from_slice(&[0x80 | 42, 0x88]);
+        expected.extend_from_slice(&[0xff, 11, 10, 9, 0x80]);
+        // Index of the third pixel
+        expected.push(49);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(bytes, expected);
+    }
+}
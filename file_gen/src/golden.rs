@@ -0,0 +1,249 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use olive_rs::{
+    diff_image, Canvas, DiffError, HeapPixels2D, ImageDiff, Pixel, Pixels2D, Tolerance,
+};
+
+use crate::{load_from_png_file, save_to_png_file, DecodeError};
+
+/// Set to anything but `0` to rewrite golden images instead of failing
+pub const UPDATE_GOLDEN_ENV: &str = "OLIVE_UPDATE_GOLDEN";
+
+/// Compares rendered pixels with golden PNG files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenTest {
+    output_dir: PathBuf,
+    tolerance: Tolerance,
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The golden file does not exist and the rendered image is written to `new`
+    Missing {
+        golden: PathBuf,
+        new: PathBuf,
+    },
+    Mismatch(Box<GoldenMismatch>),
+    Io(io::Error),
+    Decode(DecodeError),
+}
+
+#[derive(Debug)]
+pub struct GoldenMismatch {
+    pub golden: PathBuf,
+    pub new: PathBuf,
+    /// Missing if the sizes are different
+    pub diff: Option<PathBuf>,
+    pub result: Result<ImageDiff, DiffError>,
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Missing { golden, new } => write!(
+                f,
+                "golden {} is missing\n  new: {}\nset {UPDATE_GOLDEN_ENV}=1 to create it",
+                golden.display(),
+                new.display()
+            ),
+            GoldenError::Mismatch(mismatch) => {
+                let GoldenMismatch {
+                    golden,
+                    new,
+                    diff,
+                    result,
+                } = mismatch.as_ref();
+                match result {
+                    Ok(diff) => write!(
+                        f,
+                        "golden {} does not match: max delta {}, RMSE {:.3}, PSNR {:.2} dB, {} pixels above {}",
+                        golden.display(),
+                        diff.max_delta,
+                        diff.rmse,
+                        diff.psnr,
+                        diff.pixels_above_threshold,
+                        diff.tolerance.threshold
+                    )?,
+                    Err(e) => write!(f, "golden {} does not match: {e}", golden.display())?,
+                }
+                write!(f, "\n  old: {}\n  new: {}", golden.display(), new.display())?;
+                if let Some(diff) = diff {
+                    write!(f, "\n  diff: {}", diff.display())?;
+                }
+                write!(f, "\nset {UPDATE_GOLDEN_ENV}=1 to update it")
+            }
+            GoldenError::Io(e) => write!(f, "{e}"),
+            GoldenError::Decode(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GoldenError::Io(e) => Some(e),
+            GoldenError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+impl From<DecodeError> for GoldenError {
+    fn from(e: DecodeError) -> Self {
+        GoldenError::Decode(e)
+    }
+}
+
+impl GoldenTest {
+    /// New and diff images of mismatches are written into `output_dir`
+    pub fn new<P>(output_dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    /// Exact matches are required by default
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn is_update_mode() -> bool {
+        std::env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|v| !v.is_empty() && v != "0")
+    }
+
+    /// Compare `actual` with the golden PNG, or rewrite the golden in update mode
+    pub fn check<P, CP>(&self, golden: P, actual: &CP) -> Result<(), GoldenError>
+    where
+        P: AsRef<Path>,
+        CP: Pixels2D,
+    {
+        let golden = golden.as_ref();
+        if Self::is_update_mode() {
+            if let Some(dir) = golden.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            save_to_png_file(actual, golden)?;
+            return Ok(());
+        }
+
+        let stem = golden.file_stem().unwrap_or_default().to_string_lossy();
+        let new = self.output_dir.join(format!("{stem}.new.png"));
+        if !golden.exists() {
+            std::fs::create_dir_all(&self.output_dir)?;
+            save_to_png_file(actual, &new)?;
+            return Err(GoldenError::Missing {
+                golden: golden.to_path_buf(),
+                new,
+            });
+        }
+
+        let expected = load_from_png_file(golden)?;
+        let result = ImageDiff::new(&expected, actual, self.tolerance);
+        if result.as_ref().is_ok_and(|d| d.is_within_tolerance()) {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.output_dir)?;
+        save_to_png_file(actual, &new)?;
+        let diff = match diff_image(&expected, actual, self.tolerance.threshold) {
+            Ok(image) => {
+                let path = self.output_dir.join(format!("{stem}.diff.png"));
+                save_to_png_file(&image, &path)?;
+                Some(path)
+            }
+            Err(_) => None,
+        };
+        Err(GoldenError::Mismatch(Box::new(GoldenMismatch {
+            golden: golden.to_path_buf(),
+            new,
+            diff,
+            result,
+        })))
+    }
+
+    /// Panics with the image paths if [`GoldenTest::check`] fails
+    pub fn assert<P, CP>(&self, golden: P, actual: &CP)
+    where
+        P: AsRef<Path>,
+        CP: Pixels2D,
+    {
+        if let Err(e) = self.check(golden, actual) {
+            panic!("{e}");
+        }
+    }
+
+    /// Draw a `width` by `height` scene filled with `background` and [`GoldenTest::assert`] it
+    pub fn assert_scene<P, F>(
+        &self,
+        golden: P,
+        width: usize,
+        height: usize,
+        background: Pixel,
+        draw: F,
+    ) where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Canvas<'_, HeapPixels2D>),
+    {
+        let mut pixels = HeapPixels2D::new(width, height, background);
+        draw(&mut Canvas::new_entire(&mut pixels));
+        self.assert(golden, &pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        if GoldenTest::is_update_mode() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "olive_rs_golden_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let golden_test = GoldenTest::new(dir.join("out"));
+        let golden = dir.join("golden.png");
+
+        let mut pixels = HeapPixels2D::new(2, 2, Pixel::new(0, 0, 0, 0xff));
+        let result = golden_test.check(&golden, &pixels);
+        assert!(matches!(result, Err(GoldenError::Missing { .. })));
+        assert!(dir.join("out/golden.new.png").exists());
+
+        save_to_png_file(&pixels, &golden).unwrap();
+        golden_test.check(&golden, &pixels).unwrap();
+
+        pixels.pixels_mut()[3] = Pixel::new(1, 0, 0, 0xff);
+        let Err(GoldenError::Mismatch(mismatch)) = golden_test.check(&golden, &pixels) else {
+            panic!("expected a mismatch");
+        };
+        assert!(mismatch.diff.unwrap().exists());
+        assert_eq!(mismatch.result.unwrap().max_delta, 1);
+
+        let tolerant = golden_test.with_tolerance(Tolerance {
+            threshold: 1,
+            max_pixels: 0,
+        });
+        tolerant.check(&golden, &pixels).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
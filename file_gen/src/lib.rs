@@ -5,6 +5,32 @@ use std::{
 
 use olive_rs::Pixels2D;
 
+mod animation;
+mod decode;
+mod encode;
+mod golden;
+mod png_options;
+mod video;
+
+pub use animation::{
+    save_to_apng_file, save_to_apng_stream, save_to_gif_file, save_to_gif_stream, AnimationOptions,
+    Dithering, GifOptions,
+};
+pub use decode::{
+    load_from_bmp_file, load_from_bmp_stream, load_from_png_file, load_from_png_stream,
+    load_from_ppm_file, load_from_ppm_stream, DecodeError,
+};
+pub use encode::{
+    save_to_bmp_file, save_to_bmp_stream, save_to_qoi_file, save_to_qoi_stream, save_to_svg_file,
+    save_to_svg_stream, save_to_tga_file, save_to_tga_stream,
+};
+pub use golden::{GoldenError, GoldenMismatch, GoldenTest, UPDATE_GOLDEN_ENV};
+pub use png_options::{
+    save_to_png_file_with_options, save_to_png_stream_with_options, PngCompression, PngFilter,
+    PngOptions,
+};
+pub use video::{save_to_png_sequence, save_to_y4m_file, save_to_y4m_stream, VideoOptions};
+
 fn create_file<P>(file_path: P) -> io::Result<std::io::BufWriter<std::fs::File>>
 where
     P: AsRef<Path>,
@@ -25,6 +51,7 @@ where
     Ok(())
 }
 
+/// Alpha is dropped by premultiplying, which is the same as compositing over black
 pub fn save_to_ppm_stream<S, CP>(pixels: &CP, stream: &mut S) -> io::Result<()>
 where
     S: Write,
@@ -50,24 +77,11 @@ where
     Ok(())
 }
 
+/// 8-bit RGBA with default settings; see [`save_to_png_stream_with_options`] for more control
 pub fn save_to_png_stream<S, CP>(pixels: &CP, stream: &mut S) -> io::Result<()>
 where
     S: Write,
     CP: Pixels2D,
 {
-    assert!(pixels.width() <= u32::MAX as usize);
-    assert!(pixels.height() <= u32::MAX as usize);
-    let mut encoder = png::Encoder::new(stream, pixels.width() as u32, pixels.height() as u32);
-    encoder.set_color(png::ColorType::Rgba);
-    encoder.set_depth(png::BitDepth::Eight);
-    let mut writer = encoder.write_header()?;
-    let mut png_pixels = Vec::new();
-    for pixel in pixels.pixels() {
-        png_pixels.push(pixel.r());
-        png_pixels.push(pixel.g());
-        png_pixels.push(pixel.b());
-        png_pixels.push(pixel.a());
-    }
-    writer.write_image_data(&png_pixels)?;
-    Ok(())
+    save_to_png_stream_with_options(pixels, &PngOptions::default(), stream)
 }
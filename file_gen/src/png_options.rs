@@ -0,0 +1,277 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use olive_rs::{Pixel, Pixels2D};
+
+use crate::create_file;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngCompression {
+    /// What `png::Encoder` uses unless told otherwise, so [`crate::save_to_png_stream`] writes it.
+    ///
+    /// Quick deflate with the [`PngFilter`], or stored rows without a filter if that is smaller.
+    #[default]
+    Fast,
+    /// `png::Compression::Default`: smaller files than `Fast` at a moderate speed
+    Balanced,
+    /// Smallest files but slowest
+    Best,
+}
+
+/// - Ref: <https://www.w3.org/TR/png/#9Filter-types>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngFilter {
+    None,
+    #[default]
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the filter for each row
+    Adaptive,
+}
+
+/// The default options write the same file as [`crate::save_to_png_stream`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PngOptions {
+    pub compression: PngCompression,
+    pub filter: PngFilter,
+    /// Write RGB without alpha by compositing the pixels over this color
+    pub background: Option<Pixel>,
+    pub title: Option<String>,
+    pub software: Option<String>,
+    pub creation_time: Option<SystemTime>,
+    /// Dots per inch for both axes
+    pub dpi: Option<u32>,
+}
+
+pub fn save_to_png_file_with_options<P, CP>(
+    pixels: &CP,
+    options: &PngOptions,
+    file_path: P,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    CP: Pixels2D,
+{
+    let mut file = create_file(file_path)?;
+    save_to_png_stream_with_options(pixels, options, &mut file)?;
+    Ok(())
+}
+
+pub fn save_to_png_stream_with_options<S, CP>(
+    pixels: &CP,
+    options: &PngOptions,
+    stream: &mut S,
+) -> io::Result<()>
+where
+    S: Write,
+    CP: Pixels2D,
+{
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "image is too large for PNG");
+    let width = u32::try_from(pixels.width()).map_err(|_| too_large())?;
+    let height = u32::try_from(pixels.height()).map_err(|_| too_large())?;
+    let mut encoder = png::Encoder::new(stream, width, height);
+    let color = match options.background {
+        Some(_) => png::ColorType::Rgb,
+        None => png::ColorType::Rgba,
+    };
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(match options.compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Balanced => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+    match options.filter {
+        PngFilter::Adaptive => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+        filter => encoder.set_filter(match filter {
+            PngFilter::None => png::FilterType::NoFilter,
+            PngFilter::Sub => png::FilterType::Sub,
+            PngFilter::Up => png::FilterType::Up,
+            PngFilter::Avg => png::FilterType::Avg,
+            PngFilter::Paeth => png::FilterType::Paeth,
+            PngFilter::Adaptive => unreachable!(),
+        }),
+    }
+
+    // Keywords are predefined in <https://www.w3.org/TR/png/#11keywords>
+    if let Some(title) = &options.title {
+        encoder.add_text_chunk("Title".to_string(), title.clone())?;
+    }
+    if let Some(software) = &options.software {
+        encoder.add_text_chunk("Software".to_string(), software.clone())?;
+    }
+    if let Some(time) = options.creation_time {
+        encoder.add_text_chunk("Creation Time".to_string(), rfc_1123(time))?;
+    }
+    if let Some(dpi) = options.dpi {
+        const METERS_PER_INCH: f64 = 0.0254;
+        let ppm = (dpi as f64 / METERS_PER_INCH).round() as u32;
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: ppm,
+            yppu: ppm,
+            unit: png::Unit::Meter,
+        }));
+    }
+
+    let mut writer = encoder.write_header()?;
+    let mut png_pixels = Vec::new();
+    for pixel in pixels.pixels() {
+        match options.background {
+            Some(background) => {
+                let pixel = pixel.over(background);
+                png_pixels.extend_from_slice(&[pixel.r(), pixel.g(), pixel.b()]);
+            }
+            None => png_pixels.extend_from_slice(&[pixel.r(), pixel.g(), pixel.b(), pixel.a()]),
+        }
+    }
+    writer.write_image_data(&png_pixels)?;
+    Ok(())
+}
+
+/// Format like `Tue, 18 Oct 2022 09:30:00 +0000`, which the PNG spec recommends for `Creation Time`
+///
+/// - Ref: <https://www.rfc-editor.org/rfc/rfc1123#page-55>
+fn rfc_1123(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // Civil date from days since the epoch
+    // - Ref: <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use olive_rs::HeapPixels2D;
+
+    use crate::save_to_png_stream;
+
+    use super::*;
+
+    fn test_pixels() -> HeapPixels2D {
+        let pixels = vec![
+            Pixel::new(0xff, 0, 0, 0xff),
+            Pixel::new(0, 0xff, 0, 0),
+            Pixel::new(0, 0, 0xff, 0x80),
+            Pixel::new(1, 2, 3, 4),
+        ];
+        HeapPixels2D::from_pixels(2, 2, pixels)
+    }
+
+    #[test]
+    fn default_options() {
+        let pixels = test_pixels();
+        let mut bytes = Vec::new();
+        save_to_png_stream(&pixels, &mut bytes).unwrap();
+        let decoded = crate::load_from_png_stream(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, pixels);
+    }
+
+    /// The baseline `save_to_png_stream` only set the color and depth
+    #[test]
+    fn same_as_plain_encoder() {
+        let pixels = test_pixels();
+        let mut bytes = Vec::new();
+        save_to_png_stream(&pixels, &mut bytes).unwrap();
+
+        let mut expected = Vec::new();
+        let mut encoder = png::Encoder::new(&mut expected, 2, 2);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data = pixels
+            .pixels()
+            .iter()
+            .flat_map(|p| [p.r(), p.g(), p.b(), p.a()])
+            .collect::<Vec<_>>();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&data)
+            .unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn flatten_and_metadata() {
+        let options = PngOptions {
+            compression: PngCompression::Best,
+            filter: PngFilter::Adaptive,
+            background: Some(Pixel::new(0xff, 0xff, 0xff, 0xff)),
+            title: Some("Olive".to_string()),
+            software: Some("olive-rs".to_string()),
+            creation_time: Some(UNIX_EPOCH + Duration::from_secs(1_666_085_400)),
+            dpi: Some(300),
+        };
+        let mut bytes = Vec::new();
+        save_to_png_stream_with_options(&test_pixels(), &options, &mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        let texts = info
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.as_str(), chunk.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                ("Title", "Olive"),
+                ("Software", "olive-rs"),
+                ("Creation Time", "Tue, 18 Oct 2022 09:30:00 +0000"),
+            ]
+        );
+        let dims = info.pixel_dims.unwrap();
+        assert_eq!(
+            (dims.xppu, dims.yppu, dims.unit),
+            (11811, 11811, png::Unit::Meter)
+        );
+
+        let mut buf = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buf).unwrap();
+        assert_eq!(&buf[..6], [0xff, 0, 0, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn rfc_1123_dates() {
+        assert_eq!(rfc_1123(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 +0000");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661);
+        assert_eq!(rfc_1123(leap_day), "Tue, 29 Feb 2000 01:01:01 +0000");
+    }
+}
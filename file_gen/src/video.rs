@@ -0,0 +1,257 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use olive_rs::{HeapPixels2D, Render};
+
+use crate::{animation::for_each_frame, create_file, save_to_png_file, AnimationOptions};
+
+/// How to step a [`Render`] implementation for a video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoOptions {
+    /// Size of [`Render::pixels`]
+    pub width: usize,
+    pub height: usize,
+    /// Writing fails for 0
+    pub fps: u32,
+    pub duration_ms: f64,
+}
+
+impl VideoOptions {
+    pub fn frames(&self) -> usize {
+        (self.duration_ms * self.fps as f64 / 1000.).round() as usize
+    }
+
+    pub fn dt_ms(&self) -> f64 {
+        1000. / self.fps as f64
+    }
+
+    fn animation(&self) -> io::Result<AnimationOptions> {
+        if self.fps == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "fps must be positive",
+            ));
+        }
+        Ok(AnimationOptions {
+            width: self.width,
+            height: self.height,
+            frames: self.frames(),
+            dt_ms: self.dt_ms(),
+            plays: 0,
+        })
+    }
+}
+
+/// Write frames as `000000.png`, `000001.png`, ... into `directory`.
+///
+/// `progress` is called with the number of written frames and the total after each frame.
+///
+/// The sequence can be encoded by `ffmpeg -framerate <fps> -i %06d.png <output>`.
+pub fn save_to_png_sequence<P, R, F>(
+    render: &mut R,
+    options: &VideoOptions,
+    directory: P,
+    mut progress: F,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    R: Render,
+    F: FnMut(usize, usize),
+{
+    let animation = options.animation()?;
+    let directory = directory.as_ref();
+    std::fs::create_dir_all(directory)?;
+    for_each_frame(render, &animation, |i, pixels| {
+        let pixels = HeapPixels2D::from_pixels(options.width, options.height, pixels.to_vec());
+        save_to_png_file(&pixels, directory.join(format!("{i:06}.png")))?;
+        progress(i + 1, animation.frames);
+        Ok(())
+    })
+}
+
+pub fn save_to_y4m_file<P, R, F>(
+    render: &mut R,
+    options: &VideoOptions,
+    file_path: P,
+    progress: F,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    R: Render,
+    F: FnMut(usize, usize),
+{
+    let mut file = create_file(file_path)?;
+    save_to_y4m_stream(render, options, &mut file, progress)?;
+    Ok(())
+}
+
+/// YUV 4:2:0 in BT.601 limited range with alpha premultiplied on black like the PPM output.
+///
+/// `progress` is called with the number of written frames and the total after each frame.
+///
+/// - Ref: <https://wiki.multimedia.cx/index.php/YUV4MPEG2>
+pub fn save_to_y4m_stream<S, R, F>(
+    render: &mut R,
+    options: &VideoOptions,
+    stream: &mut S,
+    mut progress: F,
+) -> io::Result<()>
+where
+    S: Write,
+    R: Render,
+    F: FnMut(usize, usize),
+{
+    let animation = options.animation()?;
+    let (width, height) = (options.width, options.height);
+    stream.write_all(
+        format!(
+            "YUV4MPEG2 W{width} H{height} F{}:1 Ip A1:1 C420jpeg\n",
+            options.fps
+        )
+        .as_bytes(),
+    )?;
+
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut y_plane = vec![0; width * height];
+    let mut u_plane = vec![0; chroma_width * chroma_height];
+    let mut v_plane = vec![0; chroma_width * chroma_height];
+    for_each_frame(render, &animation, |i, pixels| {
+        let rgb = |x: usize, y: usize| -> [f64; 3] {
+            let pixel = pixels[y * width + x];
+            let a = pixel.a() as f64 / (u8::MAX as f64).powi(2);
+            [pixel.r(), pixel.g(), pixel.b()].map(|c| c as f64 * a)
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let [r, g, b] = rgb(x, y);
+                y_plane[y * width + x] = to_u8(16. + 65.481 * r + 128.553 * g + 24.966 * b);
+            }
+        }
+        // Average each 2x2 block
+        for cy in 0..chroma_height {
+            for cx in 0..chroma_width {
+                let mut sum = [0.; 3];
+                let mut n = 0.;
+                for y in cy * 2..(cy * 2 + 2).min(height) {
+                    for x in cx * 2..(cx * 2 + 2).min(width) {
+                        let c = rgb(x, y);
+                        (0..3).for_each(|i| sum[i] += c[i]);
+                        n += 1.;
+                    }
+                }
+                let [r, g, b] = sum.map(|s| s / n);
+                let i = cy * chroma_width + cx;
+                u_plane[i] = to_u8(128. - 37.797 * r - 74.203 * g + 112. * b);
+                v_plane[i] = to_u8(128. + 112. * r - 93.786 * g - 18.214 * b);
+            }
+        }
+
+        stream.write_all(b"FRAME\n")?;
+        stream.write_all(&y_plane)?;
+        stream.write_all(&u_plane)?;
+        stream.write_all(&v_plane)?;
+        progress(i + 1, animation.frames);
+        Ok(())
+    })
+}
+
+fn to_u8(value: f64) -> u8 {
+    value.round().clamp(0., u8::MAX as f64) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use olive_rs::{Pixel, Pixels2D};
+
+    use super::*;
+
+    /// Solid color changing from black to white after the first frame
+    struct Flash {
+        pixels: Vec<Pixel>,
+    }
+
+    impl Render for Flash {
+        fn render(&mut self, dt_ms: f64) {
+            let color = if dt_ms > 0. { 0xff } else { 0 };
+            self.pixels.fill(Pixel::new(color, color, color, 0xff));
+        }
+
+        fn pixels(&self) -> &[Pixel] {
+            &self.pixels
+        }
+    }
+
+    fn options() -> VideoOptions {
+        VideoOptions {
+            width: 3,
+            height: 2,
+            fps: 30,
+            duration_ms: 100.,
+        }
+    }
+
+    #[test]
+    fn y4m() {
+        let mut render = Flash {
+            pixels: vec![Pixel::new(0, 0, 0, 0); 6],
+        };
+        let mut reported = Vec::new();
+        let mut bytes = Vec::new();
+        save_to_y4m_stream(&mut render, &options(), &mut bytes, |done, total| {
+            reported.push((done, total))
+        })
+        .unwrap();
+        assert_eq!(reported, [(1, 3), (2, 3), (3, 3)]);
+
+        let header = b"YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C420jpeg\n";
+        assert!(bytes.starts_with(header));
+        let frames = &bytes[header.len()..];
+        let frame_len = b"FRAME\n".len() + 6 + 2 + 2;
+        assert_eq!(frames.len(), frame_len * 3);
+        let black = &frames[..frame_len];
+        assert_eq!(&black[6..], [16, 16, 16, 16, 16, 16, 128, 128, 128, 128]);
+        let white = &frames[frame_len..frame_len * 2];
+        assert_eq!(
+            &white[6..],
+            [235, 235, 235, 235, 235, 235, 128, 128, 128, 128]
+        );
+    }
+
+    #[test]
+    fn png_sequence() {
+        let directory = std::env::temp_dir().join(format!(
+            "olive_rs_png_sequence_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let mut render = Flash {
+            pixels: vec![Pixel::new(0, 0, 0, 0); 6],
+        };
+        save_to_png_sequence(&mut render, &options(), &directory, |_, _| {}).unwrap();
+        let last = crate::load_from_png_file(directory.join("000002.png")).unwrap();
+        assert_eq!(last.pixels()[0], Pixel::new(0xff, 0xff, 0xff, 0xff));
+        assert!(!directory.join("000003.png").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn zero_fps() {
+        let mut render = Flash {
+            pixels: vec![Pixel::new(0, 0, 0, 0); 6],
+        };
+        let options = VideoOptions {
+            fps: 0,
+            ..options()
+        };
+        let mut bytes = Vec::new();
+        let error = save_to_y4m_stream(&mut render, &options, &mut bytes, |_, _| {}).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(bytes.is_empty());
+    }
+}
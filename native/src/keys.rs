@@ -0,0 +1,97 @@
+use minifb::Key;
+use olive_rs::{KeyEvent, Modifiers};
+
+/// A [`KeyEvent`] named like the DOM `key` and `code` values, assuming a US layout
+///
+/// - Ref: <https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_code_values>
+pub(crate) fn key_event(key: Key, repeat: bool, modifiers: Modifiers) -> KeyEvent {
+    let (code, name) = names(key);
+    let key = match name {
+        Name::Char(c) if modifiers.shift => c.to_ascii_uppercase().to_string(),
+        Name::Char(c) => c.to_string(),
+        Name::Named(name) => name.to_string(),
+        Name::Code => code.clone(),
+    };
+    KeyEvent {
+        key,
+        code,
+        repeat,
+        modifiers,
+    }
+}
+
+enum Name {
+    Char(char),
+    Named(&'static str),
+    /// The same as the code
+    Code,
+}
+
+fn names(key: Key) -> (String, Name) {
+    let index = key as u8;
+    if key <= Key::Key9 {
+        let c = (b'0' + index) as char;
+        return (format!("Digit{c}"), Name::Char(c));
+    }
+    if key <= Key::Z {
+        let c = (b'a' + index - Key::A as u8) as char;
+        return (format!("Key{}", c.to_ascii_uppercase()), Name::Char(c));
+    }
+    if (Key::F1..=Key::F15).contains(&key) {
+        return (format!("F{}", index - Key::F1 as u8 + 1), Name::Code);
+    }
+    if (Key::NumPad0..=Key::NumPad9).contains(&key) {
+        let c = (b'0' + index - Key::NumPad0 as u8) as char;
+        return (format!("Numpad{c}"), Name::Char(c));
+    }
+
+    let (code, name) = match key {
+        Key::Down => ("ArrowDown", Name::Code),
+        Key::Left => ("ArrowLeft", Name::Code),
+        Key::Right => ("ArrowRight", Name::Code),
+        Key::Up => ("ArrowUp", Name::Code),
+        Key::Apostrophe => ("Quote", Name::Char('\'')),
+        Key::Backquote => ("Backquote", Name::Char('`')),
+        Key::Backslash => ("Backslash", Name::Char('\\')),
+        Key::Comma => ("Comma", Name::Char(',')),
+        Key::Equal => ("Equal", Name::Char('=')),
+        Key::LeftBracket => ("BracketLeft", Name::Char('[')),
+        Key::Minus => ("Minus", Name::Char('-')),
+        Key::Period => ("Period", Name::Char('.')),
+        Key::RightBracket => ("BracketRight", Name::Char(']')),
+        Key::Semicolon => ("Semicolon", Name::Char(';')),
+        Key::Slash => ("Slash", Name::Char('/')),
+        Key::Backspace => ("Backspace", Name::Code),
+        Key::Delete => ("Delete", Name::Code),
+        Key::End => ("End", Name::Code),
+        Key::Enter => ("Enter", Name::Code),
+        Key::Escape => ("Escape", Name::Code),
+        Key::Home => ("Home", Name::Code),
+        Key::Insert => ("Insert", Name::Code),
+        Key::Menu => ("ContextMenu", Name::Code),
+        Key::PageDown => ("PageDown", Name::Code),
+        Key::PageUp => ("PageUp", Name::Code),
+        Key::Pause => ("Pause", Name::Code),
+        Key::Space => ("Space", Name::Char(' ')),
+        Key::Tab => ("Tab", Name::Code),
+        Key::NumLock => ("NumLock", Name::Code),
+        Key::CapsLock => ("CapsLock", Name::Code),
+        Key::ScrollLock => ("ScrollLock", Name::Code),
+        Key::LeftShift => ("ShiftLeft", Name::Named("Shift")),
+        Key::RightShift => ("ShiftRight", Name::Named("Shift")),
+        Key::LeftCtrl => ("ControlLeft", Name::Named("Control")),
+        Key::RightCtrl => ("ControlRight", Name::Named("Control")),
+        Key::NumPadDot => ("NumpadDecimal", Name::Char('.')),
+        Key::NumPadSlash => ("NumpadDivide", Name::Char('/')),
+        Key::NumPadAsterisk => ("NumpadMultiply", Name::Char('*')),
+        Key::NumPadMinus => ("NumpadSubtract", Name::Char('-')),
+        Key::NumPadPlus => ("NumpadAdd", Name::Char('+')),
+        Key::NumPadEnter => ("NumpadEnter", Name::Named("Enter")),
+        Key::LeftAlt => ("AltLeft", Name::Named("Alt")),
+        Key::RightAlt => ("AltRight", Name::Named("Alt")),
+        Key::LeftSuper => ("MetaLeft", Name::Named("Meta")),
+        Key::RightSuper => ("MetaRight", Name::Named("Meta")),
+        _ => ("Unidentified", Name::Code),
+    };
+    (code.to_string(), name)
+}
@@ -0,0 +1,380 @@
+//! Show a [`Render`] in a native window with a software framebuffer,
+//! or render it without a window for CI.
+
+use std::{fmt, time::Instant};
+
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use olive_rs::{CanvasError, Event, HeapPixels2D, Modifiers, Pixel, PointerButton, Render};
+
+mod keys;
+
+pub use minifb::Scale;
+
+/// Set to anything but `0` to render without a window
+pub const HEADLESS_ENV: &str = "OLIVE_HEADLESS";
+
+/// Scroll distance of a wheel notch in pixels, three lines of 16 pixels like in browsers
+const WHEEL_NOTCH_PX: f64 = 48.;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowOptions<'a> {
+    pub title: &'a str,
+    /// Window pixels per rendered pixel
+    pub scale: Scale,
+    /// Must be positive
+    pub fps: f64,
+    /// Translucent pixels are composited over this color
+    pub background: Pixel,
+    /// How many frames to render in headless mode
+    pub headless_frames: usize,
+}
+
+impl Default for WindowOptions<'_> {
+    fn default() -> Self {
+        Self {
+            title: "Olive-rs",
+            scale: Scale::X1,
+            fps: 60.,
+            background: Pixel::new(0, 0, 0, u8::MAX),
+            headless_frames: 60,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WindowError {
+    Window(minifb::Error),
+    Canvas(CanvasError),
+    /// [`WindowOptions::fps`] is not positive
+    InvalidFps(f64),
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::Window(e) => write!(f, "{e}"),
+            WindowError::Canvas(e) => write!(f, "{e}"),
+            WindowError::InvalidFps(fps) => write!(f, "fps must be positive, got {fps}"),
+        }
+    }
+}
+
+impl std::error::Error for WindowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WindowError::Window(e) => Some(e),
+            WindowError::Canvas(e) => Some(e),
+            WindowError::InvalidFps(_) => None,
+        }
+    }
+}
+
+impl From<minifb::Error> for WindowError {
+    fn from(e: minifb::Error) -> Self {
+        WindowError::Window(e)
+    }
+}
+
+impl From<CanvasError> for WindowError {
+    fn from(e: CanvasError) -> Self {
+        WindowError::Canvas(e)
+    }
+}
+
+/// Whether [`run_window`] renders without a window, because of [`HEADLESS_ENV`] or a missing display
+pub fn is_headless() -> bool {
+    if std::env::var_os(HEADLESS_ENV).is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    cfg!(all(unix, not(target_os = "macos")))
+        && std::env::var_os("DISPLAY").is_none()
+        && std::env::var_os("WAYLAND_DISPLAY").is_none()
+}
+
+/// Show `render` in a window until it is closed.
+///
+/// `width` and `height` are the size of [`Render::pixels`].
+/// Like in the `wasm` runner, [`Event::Resize`] is sent before the first frame,
+/// which is rendered with a `dt_ms` of 0.
+/// In [headless mode](is_headless) this is [`run_headless`] with `options.headless_frames` frames.
+pub fn run_window<R>(
+    width: usize,
+    height: usize,
+    render: &mut R,
+    options: &WindowOptions,
+) -> Result<(), WindowError>
+where
+    R: Render,
+{
+    if options.fps.is_nan() || options.fps <= 0. {
+        return Err(WindowError::InvalidFps(options.fps));
+    }
+    if is_headless() {
+        run_headless(
+            width,
+            height,
+            render,
+            options.headless_frames,
+            1000. / options.fps,
+        )?;
+        return Ok(());
+    }
+
+    let mut window = Window::new(
+        options.title,
+        width,
+        height,
+        minifb::WindowOptions {
+            scale: options.scale,
+            ..Default::default()
+        },
+    )?;
+    // 0 would mean uncapped
+    window.set_target_fps(options.fps.round().max(1.) as usize);
+    render.event(&Event::Resize { width, height });
+
+    let mut input = Input::default();
+    let mut buffer = vec![0; width * height];
+    let mut last_frame: Option<Instant> = None;
+    while window.is_open() {
+        for event in input.poll(&mut window) {
+            render.event(&event);
+        }
+
+        let now = Instant::now();
+        let dt_ms = match last_frame {
+            Some(t) => now.duration_since(t).as_secs_f64() * 1000.,
+            None => 0.,
+        };
+        last_frame = Some(now);
+
+        render.update(dt_ms);
+        render.render(dt_ms);
+        let pixels = render.pixels();
+        check_size(pixels, width, height)?;
+        fill_buffer(pixels, options.background, &mut buffer);
+        window.update_with_buffer(&buffer, width, height)?;
+    }
+    Ok(())
+}
+
+/// Render `frames` frames without a window and return the last one.
+///
+/// The first frame is rendered with a `dt_ms` of 0 and the others with `dt_ms`,
+/// as fast as possible.
+pub fn run_headless<R>(
+    width: usize,
+    height: usize,
+    render: &mut R,
+    frames: usize,
+    dt_ms: f64,
+) -> Result<HeapPixels2D, CanvasError>
+where
+    R: Render,
+{
+    render.event(&Event::Resize { width, height });
+    for frame in 0..frames {
+        let dt_ms = if frame == 0 { 0. } else { dt_ms };
+        render.update(dt_ms);
+        render.render(dt_ms);
+        check_size(render.pixels(), width, height)?;
+    }
+    HeapPixels2D::try_from_pixels(width, height, render.pixels().to_vec())
+}
+
+fn check_size(pixels: &[Pixel], width: usize, height: usize) -> Result<(), CanvasError> {
+    if pixels.len() != width * height {
+        return Err(CanvasError::SizeMismatch {
+            width,
+            height,
+            len: pixels.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Opaque `0RGB` colors as used by `minifb`
+fn fill_buffer(pixels: &[Pixel], background: Pixel, buffer: &mut [u32]) {
+    for (b, p) in buffer.iter_mut().zip(pixels) {
+        let p = p.over(background);
+        *b = (p.r() as u32) << 16 | (p.g() as u32) << 8 | p.b() as u32;
+    }
+}
+
+/// Input state of the previous frame to turn `minifb` polling into events
+#[derive(Debug, Default)]
+struct Input {
+    pointer: Option<(f32, f32)>,
+    buttons: [bool; 3],
+    keys: Vec<Key>,
+    active: Option<bool>,
+}
+
+impl Input {
+    const BUTTONS: [(MouseButton, PointerButton); 3] = [
+        (MouseButton::Left, PointerButton::Primary),
+        (MouseButton::Right, PointerButton::Secondary),
+        (MouseButton::Middle, PointerButton::Middle),
+    ];
+
+    fn poll(&mut self, window: &mut Window) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        let active = window.is_active();
+        if self.active != Some(active) {
+            // Only report changes after the initial state
+            if self.active.is_some() {
+                events.push(Event::Focus(active));
+            }
+            self.active = Some(active);
+        }
+
+        let pointer = window.get_mouse_pos(MouseMode::Discard);
+        if let Some((x, y)) = pointer {
+            if self.pointer != pointer {
+                events.push(Event::PointerMove {
+                    x: x as f64,
+                    y: y as f64,
+                });
+            }
+        }
+        self.pointer = pointer.or(self.pointer);
+
+        let (x, y) = self.pointer.unwrap_or_default();
+        let (x, y) = (x as f64, y as f64);
+        for (down, (mouse_button, button)) in self.buttons.iter_mut().zip(Self::BUTTONS) {
+            let is_down = window.get_mouse_down(mouse_button);
+            if is_down && !*down {
+                events.push(Event::PointerDown { x, y, button });
+            } else if !is_down && *down {
+                events.push(Event::PointerUp { x, y, button });
+            }
+            *down = is_down;
+        }
+
+        if let Some((dx, dy)) = window.get_scroll_wheel() {
+            // `minifb` scrolls up and left for positive values, the DOM does the opposite
+            events.push(Event::Wheel {
+                dx: -dx as f64 * WHEEL_NOTCH_PX,
+                dy: -dy as f64 * WHEEL_NOTCH_PX,
+            });
+        }
+
+        let is_down = |keys: [Key; 2]| keys.into_iter().any(|k| window.is_key_down(k));
+        let modifiers = Modifiers {
+            shift: is_down([Key::LeftShift, Key::RightShift]),
+            ctrl: is_down([Key::LeftCtrl, Key::RightCtrl]),
+            alt: is_down([Key::LeftAlt, Key::RightAlt]),
+            meta: is_down([Key::LeftSuper, Key::RightSuper]),
+        };
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            let repeat = self.keys.contains(&key);
+            events.push(Event::KeyDown(keys::key_event(key, repeat, modifiers)));
+        }
+        for key in window.get_keys_released() {
+            events.push(Event::KeyUp(keys::key_event(key, false, modifiers)));
+        }
+        self.keys = window.get_keys();
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use olive_rs::Pixels2D;
+
+    use super::*;
+
+    /// Counts frames into the red channel
+    struct Counter {
+        pixels: HeapPixels2D,
+        elapsed_ms: f64,
+    }
+
+    impl Render for Counter {
+        fn render(&mut self, dt_ms: f64) {
+            self.elapsed_ms += dt_ms;
+            let p = self.pixels.pixels()[0];
+            self.pixels.pixels_mut()[0] = Pixel::new(p.r() + 1, 0, 0, u8::MAX);
+        }
+
+        fn pixels(&self) -> &[Pixel] {
+            self.pixels.pixels()
+        }
+
+        fn event(&mut self, event: &Event) {
+            if let Event::Resize { width, height } = *event {
+                self.pixels = HeapPixels2D::new(width, height, Pixel::new(0, 0, 0, u8::MAX));
+            }
+        }
+    }
+
+    #[test]
+    fn headless() {
+        let mut counter = Counter {
+            pixels: HeapPixels2D::new(1, 1, Pixel::new(0, 0, 0, 0)),
+            elapsed_ms: 0.,
+        };
+        let last = run_headless(2, 1, &mut counter, 3, 10.).unwrap();
+        assert_eq!(last.pixels()[0], Pixel::new(3, 0, 0, u8::MAX));
+        assert_eq!(counter.elapsed_ms, 20.);
+
+        assert_eq!(
+            check_size(last.pixels(), 1, 1),
+            Err(CanvasError::SizeMismatch {
+                width: 1,
+                height: 1,
+                len: 2
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_fps() {
+        let mut counter = Counter {
+            pixels: HeapPixels2D::new(1, 1, Pixel::new(0, 0, 0, 0)),
+            elapsed_ms: 0.,
+        };
+        for fps in [0., -1., f64::NAN] {
+            let options = WindowOptions {
+                fps,
+                ..Default::default()
+            };
+            let result = run_window(1, 1, &mut counter, &options);
+            assert!(matches!(result, Err(WindowError::InvalidFps(_))));
+        }
+        assert_eq!(counter.elapsed_ms, 0.);
+    }
+
+    #[test]
+    fn buffer() {
+        let mut buffer = [0; 2];
+        let pixels = [
+            Pixel::new(0x12, 0x34, 0x56, 0xff),
+            Pixel::new(0xff, 0xff, 0xff, 0),
+        ];
+        fill_buffer(&pixels, Pixel::new(0, 0, 0xff, 0xff), &mut buffer);
+        assert_eq!(buffer, [0x123456, 0x0000ff]);
+    }
+
+    #[test]
+    fn key_names() {
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let event = keys::key_event(Key::A, false, shift);
+        assert_eq!((event.key.as_str(), event.code.as_str()), ("A", "KeyA"));
+        let event = keys::key_event(Key::Key7, true, Modifiers::default());
+        assert_eq!((event.key.as_str(), event.code.as_str()), ("7", "Digit7"));
+        assert!(event.repeat);
+        let event = keys::key_event(Key::F12, false, Modifiers::default());
+        assert_eq!((event.key.as_str(), event.code.as_str()), ("F12", "F12"));
+        let event = keys::key_event(Key::LeftShift, false, shift);
+        assert_eq!(
+            (event.key.as_str(), event.code.as_str()),
+            ("Shift", "ShiftLeft")
+        );
+    }
+}
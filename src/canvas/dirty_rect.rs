@@ -0,0 +1,43 @@
+/// A region of pixels written since the last frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DirtyRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The smallest rectangle covering both; empty rectangles are ignored
+    pub fn union(&self, other: &DirtyRect) -> DirtyRect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        DirtyRect::new(x, y, right - x, bottom - y)
+    }
+
+    /// Grow to cover the pixel `(x, y)`
+    pub(crate) fn include(&mut self, x: usize, y: usize) {
+        *self = self.union(&DirtyRect::new(x, y, 1, 1));
+    }
+}
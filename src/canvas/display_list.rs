@@ -0,0 +1,378 @@
+use std::fmt::Write;
+
+use super::{text::TextLayout, Canvas, Font, Pixel, PixelPoint, PixelPointF, Pixels2D};
+
+/// A recorded call to one of the [`Canvas`] primitives
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand<'font> {
+    Fill(Pixel),
+    PixelRect {
+        p: PixelPoint,
+        w: isize,
+        h: isize,
+        color: Pixel,
+    },
+    PixelCircle {
+        c: PixelPointF,
+        r: f64,
+        color: Pixel,
+    },
+    PixelLine {
+        p1: PixelPoint,
+        p2: PixelPoint,
+        color: Pixel,
+    },
+    PixelTriangle {
+        v1: PixelPointF,
+        v2: PixelPointF,
+        v3: PixelPointF,
+        color: Pixel,
+    },
+    PixelText {
+        text: String,
+        pos: PixelPoint,
+        font: &'font Font,
+        size: usize,
+        color: Pixel,
+    },
+}
+
+/// Records draw calls with the same signatures as [`Canvas`] instead of rasterizing them.
+///
+/// The recording can be replayed into a [`Canvas`] or serialized to SVG.
+/// Pixel `(x, y)` of the canvas is the unit square from `(x, y)` to `(x + 1, y + 1)` in SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayList<'font> {
+    width: usize,
+    height: usize,
+    commands: Vec<DrawCommand<'font>>,
+}
+
+impl<'font> DisplayList<'font> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn commands(&self) -> &[DrawCommand<'font>] {
+        &self.commands
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn fill(&mut self, pixel: Pixel) {
+        self.commands.push(DrawCommand::Fill(pixel));
+    }
+
+    pub fn fill_pixel_rect(&mut self, p: PixelPoint, w: isize, h: isize, color: Pixel) {
+        self.commands
+            .push(DrawCommand::PixelRect { p, w, h, color });
+    }
+
+    pub fn fill_pixel_circle(&mut self, c: PixelPointF, r: f64, color: Pixel) {
+        self.commands.push(DrawCommand::PixelCircle { c, r, color });
+    }
+
+    pub fn draw_pixel_line(&mut self, p1: PixelPoint, p2: PixelPoint, color: Pixel) {
+        self.commands.push(DrawCommand::PixelLine { p1, p2, color });
+    }
+
+    pub fn fill_pixel_triangle(
+        &mut self,
+        v1: PixelPointF,
+        v2: PixelPointF,
+        v3: PixelPointF,
+        color: Pixel,
+    ) {
+        self.commands
+            .push(DrawCommand::PixelTriangle { v1, v2, v3, color });
+    }
+
+    pub fn pixel_text(
+        &mut self,
+        text: &str,
+        pos: PixelPoint,
+        font: &'font Font,
+        size: usize,
+        color: Pixel,
+    ) {
+        self.commands.push(DrawCommand::PixelText {
+            text: text.to_string(),
+            pos,
+            font,
+            size,
+            color,
+        });
+    }
+
+    /// Rasterize the recorded calls in order
+    pub fn replay<P>(&self, canvas: &mut Canvas<'_, P>)
+    where
+        P: Pixels2D,
+    {
+        for command in &self.commands {
+            match *command {
+                DrawCommand::Fill(pixel) => canvas.fill(pixel),
+                DrawCommand::PixelRect { p, w, h, color } => canvas.fill_pixel_rect(p, w, h, color),
+                DrawCommand::PixelCircle { c, r, color } => canvas.fill_pixel_circle(c, r, color),
+                DrawCommand::PixelLine { p1, p2, color } => canvas.draw_pixel_line(p1, p2, color),
+                DrawCommand::PixelTriangle { v1, v2, v3, color } => {
+                    canvas.fill_pixel_triangle(v1, v2, v3, color)
+                }
+                DrawCommand::PixelText {
+                    ref text,
+                    pos,
+                    font,
+                    size,
+                    color,
+                } => canvas.pixel_text(text, pos, font, size, color),
+            }
+        }
+    }
+
+    /// - Ref: <https://www.w3.org/TR/SVG11/>
+    pub fn to_svg(&self) -> String {
+        let (w, h) = (self.width, self.height);
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+        )
+        .unwrap();
+        for command in &self.commands {
+            match command {
+                DrawCommand::Fill(pixel) => {
+                    writeln!(
+                        svg,
+                        r#"<rect width="100%" height="100%"{}/>"#,
+                        svg_fill(*pixel)
+                    )
+                    .unwrap();
+                }
+                DrawCommand::PixelRect { p, w, h, color } => {
+                    if *w == 0 || *h == 0 {
+                        continue;
+                    }
+                    writeln!(
+                        svg,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+                        p.x.min(p.x + w - w.signum()),
+                        p.y.min(p.y + h - h.signum()),
+                        w.abs(),
+                        h.abs(),
+                        svg_fill(*color)
+                    )
+                    .unwrap();
+                }
+                DrawCommand::PixelCircle { c, r, color } => {
+                    let (cx, cy) = center(*c);
+                    writeln!(
+                        svg,
+                        r#"<circle cx="{cx}" cy="{cy}" r="{}"{}/>"#,
+                        r.abs(),
+                        svg_fill(*color)
+                    )
+                    .unwrap();
+                }
+                DrawCommand::PixelLine { p1, p2, color } => {
+                    let (x1, y1) = center((*p1).into());
+                    let (x2, y2) = center((*p2).into());
+                    writeln!(
+                        svg,
+                        r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke-width="1" stroke-linecap="square"{}/>"#,
+                        svg_color("stroke", *color)
+                    )
+                    .unwrap();
+                }
+                DrawCommand::PixelTriangle { v1, v2, v3, color } => {
+                    let points = [v1, v2, v3].map(|v| {
+                        let (x, y) = center(*v);
+                        format!("{x},{y}")
+                    });
+                    writeln!(
+                        svg,
+                        r#"<polygon points="{}"{}/>"#,
+                        points.join(" "),
+                        svg_fill(*color)
+                    )
+                    .unwrap();
+                }
+                DrawCommand::PixelText {
+                    text,
+                    pos,
+                    font,
+                    size,
+                    color,
+                } => {
+                    // Each horizontal run of dots becomes one subpath
+                    let mut d = String::new();
+                    for (glyph, off) in TextLayout::new(text, font, *size) {
+                        let size = *size as isize;
+                        let bitmap = glyph.bitmap();
+                        for (y, row) in bitmap.chunks(glyph.width().max(1)).enumerate() {
+                            let mut x = 0;
+                            while x < row.len() {
+                                if !row[x] {
+                                    x += 1;
+                                    continue;
+                                }
+                                let start = x;
+                                while x < row.len() && row[x] {
+                                    x += 1;
+                                }
+                                let run = (x - start) as isize * size;
+                                write!(
+                                    d,
+                                    "M{} {}h{run}v{size}h-{run}z",
+                                    pos.x + off.x + start as isize * size,
+                                    pos.y + off.y + y as isize * size,
+                                )
+                                .unwrap();
+                            }
+                        }
+                    }
+                    if d.is_empty() {
+                        continue;
+                    }
+                    writeln!(svg, r#"<path d="{d}"{}/>"#, svg_fill(*color)).unwrap();
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// [`Canvas`] samples pixel `(x, y)` at `(x, y)`, which is the center of its square in SVG
+fn center(p: PixelPointF) -> (f64, f64) {
+    (p.x().to_f() + 0.5, p.y().to_f() + 0.5)
+}
+
+fn svg_fill(color: Pixel) -> String {
+    svg_color("fill", color)
+}
+
+fn svg_color(attribute: &str, color: Pixel) -> String {
+    let mut s = format!(
+        r##" {attribute}="#{:02x}{:02x}{:02x}""##,
+        color.r(),
+        color.g(),
+        color.b()
+    );
+    if color.a() != u8::MAX {
+        let opacity = color.a() as f64 / u8::MAX as f64;
+        write!(s, r#" {attribute}-opacity="{:.3}""#, opacity).unwrap();
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_font, HeapPixels2D};
+
+    use super::*;
+
+    fn record(font: &Font) -> DisplayList<'_> {
+        let mut list = DisplayList::new(20, 10);
+        list.fill(Pixel::new(0, 0, 0, 0xff));
+        list.fill_pixel_rect(
+            PixelPoint { x: 5, y: 4 },
+            -3,
+            2,
+            Pixel::new(0xff, 0, 0, 0x80),
+        );
+        list.fill_pixel_circle(PixelPointF::from_float(10, 0.5, 5, 0.), 3., Pixel::from(!0));
+        list.draw_pixel_line(
+            PixelPoint { x: 0, y: 0 },
+            PixelPoint { x: 19, y: 9 },
+            Pixel::new(0, 0xff, 0, 0xff),
+        );
+        list.fill_pixel_triangle(
+            PixelPointF::from_int(1, 1),
+            PixelPointF::from_int(8, 1),
+            PixelPointF::from_int(1, 8),
+            Pixel::new(0, 0, 0xff, 0xff),
+        );
+        list.pixel_text(
+            "-",
+            PixelPoint { x: 12, y: 1 },
+            font,
+            2,
+            Pixel::new(0, 0, 0xff, 0xff),
+        );
+        list
+    }
+
+    #[test]
+    fn replay() {
+        let font = default_font();
+        let list = record(&font);
+        assert_eq!(list.commands().len(), 6);
+
+        let mut recorded = HeapPixels2D::new(20, 10, Pixel::new(0, 0, 0, 0));
+        list.replay(&mut Canvas::new_entire(&mut recorded));
+
+        let mut direct = HeapPixels2D::new(20, 10, Pixel::new(0, 0, 0, 0));
+        let mut canvas = Canvas::new_entire(&mut direct);
+        canvas.fill(Pixel::new(0, 0, 0, 0xff));
+        canvas.fill_pixel_rect(
+            PixelPoint { x: 5, y: 4 },
+            -3,
+            2,
+            Pixel::new(0xff, 0, 0, 0x80),
+        );
+        canvas.fill_pixel_circle(PixelPointF::from_float(10, 0.5, 5, 0.), 3., Pixel::from(!0));
+        canvas.draw_pixel_line(
+            PixelPoint { x: 0, y: 0 },
+            PixelPoint { x: 19, y: 9 },
+            Pixel::new(0, 0xff, 0, 0xff),
+        );
+        canvas.fill_pixel_triangle(
+            PixelPointF::from_int(1, 1),
+            PixelPointF::from_int(8, 1),
+            PixelPointF::from_int(1, 8),
+            Pixel::new(0, 0, 0xff, 0xff),
+        );
+        canvas.pixel_text(
+            "-",
+            PixelPoint { x: 12, y: 1 },
+            &font,
+            2,
+            Pixel::new(0, 0, 0xff, 0xff),
+        );
+        assert_eq!(recorded, direct);
+    }
+
+    #[test]
+    fn svg() {
+        let font = default_font();
+        let svg = record(&font).to_svg();
+        let lines = svg.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10" viewBox="0 0 20 10">"#,
+                r##"<rect width="100%" height="100%" fill="#000000"/>"##,
+                r##"<rect x="3" y="4" width="3" height="2" fill="#ff0000" fill-opacity="0.502"/>"##,
+                r##"<circle cx="11" cy="5.5" r="3" fill="#ffffff"/>"##,
+                r##"<line x1="0.5" y1="0.5" x2="19.5" y2="9.5" stroke-width="1" stroke-linecap="square" stroke="#00ff00"/>"##,
+                r##"<polygon points="1.5,1.5 8.5,1.5 1.5,8.5" fill="#0000ff"/>"##,
+                r##"<path d="M12 5h6v2h-6z" fill="#0000ff"/>"##,
+                "</svg>",
+            ]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::ops::Range;
+
+/// Invalid sizes and coordinates passed to the `try_` constructors and accessors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanvasError {
+    /// The range of a canvas starts outside of the pixels or ends past their width
+    XRangeOutOfBounds { range: Range<usize>, width: usize },
+    /// The range of a canvas starts outside of the pixels or ends past their height
+    YRangeOutOfBounds { range: Range<usize>, height: usize },
+    /// `width * height` is not the number of pixels
+    SizeMismatch {
+        width: usize,
+        height: usize,
+        len: usize,
+    },
+    PixelOutOfBounds {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+}
+
+impl std::fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanvasError::XRangeOutOfBounds { range, width } => {
+                write!(f, "x range {range:?} is out of the width {width}")
+            }
+            CanvasError::YRangeOutOfBounds { range, height } => {
+                write!(f, "y range {range:?} is out of the height {height}")
+            }
+            CanvasError::SizeMismatch { width, height, len } => {
+                write!(f, "size {width}x{height} does not match {len} pixels")
+            }
+            CanvasError::PixelOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(f, "pixel ({x}, {y}) is out of the size {width}x{height}"),
+        }
+    }
+}
+
+impl std::error::Error for CanvasError {}
+
+/// Check that a `width` by `height` image has `len` pixels
+pub(crate) fn check_size(width: usize, height: usize, len: usize) -> Result<(), CanvasError> {
+    if width.checked_mul(height) != Some(len) {
+        return Err(CanvasError::SizeMismatch { width, height, len });
+    }
+    Ok(())
+}
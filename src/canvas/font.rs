@@ -1,21 +1,58 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::OnceLock};
 
 use crate::PixelPoint;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Font {
     glyphs: HashMap<char, Glyph>,
+    baseline: usize,
 }
 
 impl Font {
+    /// The baseline is guessed from the tallest capital letter, or the tallest glyph if the font has no capitals.
     pub fn new(glyphs: HashMap<char, Glyph>) -> Font {
-        Font { glyphs }
+        let capitals = glyphs
+            .iter()
+            .filter(|(c, _)| c.is_ascii_uppercase())
+            .map(|(_, g)| g.height())
+            .max();
+        let baseline = match capitals {
+            Some(baseline) => baseline,
+            None => glyphs.values().map(|g| g.height()).max().unwrap_or(0),
+        };
+        Font { glyphs, baseline }
     }
 
     pub fn glyph(&self, c: char) -> Option<&Glyph> {
         self.glyphs.get(&c)
     }
+
+    /// Same as [`Font::glyph`] but falls back to a filled box for missing characters
+    pub fn glyph_or_unknown(&self, c: char) -> &Glyph {
+        match self.glyph(c) {
+            Some(glyph) => glyph,
+            None => unknown_glyph_ref(),
+        }
+    }
+
+    /// Glyphs ordered by their characters
+    pub fn glyphs(&self) -> impl Iterator<Item = (char, &Glyph)> {
+        let mut glyphs = self.glyphs.iter().map(|(c, g)| (*c, g)).collect::<Vec<_>>();
+        glyphs.sort_by_key(|(c, _)| *c);
+        glyphs.into_iter()
+    }
+
+    /// Number of glyph rows above the baseline
+    pub fn baseline(&self) -> usize {
+        self.baseline
+    }
+
+    pub fn set_baseline(&mut self, baseline: usize) {
+        self.baseline = baseline;
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Glyph {
     width: usize,
     height: usize,
@@ -23,6 +60,32 @@ pub struct Glyph {
 }
 
 impl Glyph {
+    pub fn new(width: usize, height: usize, points: Vec<PixelPoint>) -> Glyph {
+        for p in &points {
+            assert!((0..width as isize).contains(&p.x));
+            assert!((0..height as isize).contains(&p.y));
+        }
+        Glyph {
+            width,
+            height,
+            points,
+        }
+    }
+
+    /// Each line is a row of the glyph and each `$` is a dot
+    pub fn from_ascii_art(s: &str) -> Glyph {
+        glyph_from_str(s)
+    }
+
+    /// `bitmap[y * width + x]` is `true` if the dot at `(x, y)` is set
+    pub(crate) fn bitmap(&self) -> Vec<bool> {
+        let mut bitmap = vec![false; self.width * self.height];
+        for p in &self.points {
+            bitmap[p.y as usize * self.width + p.x as usize] = true;
+        }
+        bitmap
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -36,7 +99,7 @@ impl Glyph {
     }
 }
 
-const DOT: char = '$';
+pub(crate) const DOT: char = '$';
 
 const A_CAP: &str = " $
 $ $
@@ -606,6 +669,11 @@ pub fn unknown_glyph() -> Glyph {
     glyph_from_str(UNKNOWN)
 }
 
+fn unknown_glyph_ref() -> &'static Glyph {
+    static UNKNOWN_GLYPH: OnceLock<Glyph> = OnceLock::new();
+    UNKNOWN_GLYPH.get_or_init(unknown_glyph)
+}
+
 fn glyph_from_str(s: &str) -> Glyph {
     let mut width = 0;
     let mut height = 0;
@@ -619,7 +687,7 @@ fn glyph_from_str(s: &str) -> Glyph {
                 });
             }
         }
-        width = width.max(line.len());
+        width = width.max(line.chars().count());
         height = y + 1;
     }
     Glyph {
@@ -727,7 +795,7 @@ pub fn default_font() -> Font {
     glyphs.insert('~', glyph_from_str(TILDE));
     glyphs.insert('|', glyph_from_str(PIPE));
 
-    Font { glyphs }
+    Font::new(glyphs)
 }
 
 #[cfg(test)]
@@ -741,4 +809,10 @@ mod tests {
         assert_eq!(glyph.height, 5);
         assert_eq!(glyph.points.len(), 10);
     }
+
+    #[test]
+    fn test_default_font_baseline() {
+        let font = default_font();
+        assert_eq!(font.baseline(), 5);
+    }
 }
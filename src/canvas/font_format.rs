@@ -0,0 +1,373 @@
+//! Two formats to store a [`Font`]:
+//!
+//! - Text: the same `$`-based ASCII art as the built-in font.
+//!
+//!   ```text
+//!   baseline 5
+//!   char A 3x5
+//!    $
+//!   $ $
+//!   $$$
+//!   $ $
+//!   $ $
+//!   char U+0020 1x1
+//!
+//!   ```
+//!
+//!   - `baseline` is optional and guessed as in [`Font::new`] if missing.
+//!     It must come before the first glyph.
+//!   - A glyph starts with `char`, followed by either the character itself or its code point like `U+0041`.
+//!   - The size `<width>x<height>` is optional.
+//!     Without it, the glyph is as wide as its longest row and trailing empty rows are ignored.
+//!   - Rows of a glyph contain only `$` and spaces.
+//!
+//! - Binary: all integers are little-endian `u32`s.
+//!   - Magic bytes `OLVF` and a version byte
+//!   - Baseline and number of glyphs
+//!   - For each glyph: code point, width, height and the dots packed row by row into bits, most significant bit first
+
+use std::collections::HashMap;
+
+use super::{
+    font::{Glyph, DOT},
+    Font, PixelPoint,
+};
+
+const MAGIC: &[u8; 4] = b"OLVF";
+const VERSION: u8 = 1;
+const CHAR_PREFIX: &str = "char ";
+const BASELINE_PREFIX: &str = "baseline ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontFormatError {
+    /// The binary data does not start with the magic bytes
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// The binary data ends in the middle of a font
+    UnexpectedEnd,
+    InvalidCodePoint(u32),
+    /// The line is malformed, counted from 1
+    InvalidLine(usize),
+    DuplicateGlyph(char),
+}
+
+impl std::fmt::Display for FontFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontFormatError::BadMagic => write!(f, "not a font file"),
+            FontFormatError::UnsupportedVersion(v) => write!(f, "unsupported font version {v}"),
+            FontFormatError::UnexpectedEnd => write!(f, "unexpected end of font data"),
+            FontFormatError::InvalidCodePoint(c) => write!(f, "invalid code point {c:#x}"),
+            FontFormatError::InvalidLine(line) => write!(f, "invalid font line {line}"),
+            FontFormatError::DuplicateGlyph(c) => write!(f, "duplicate glyph {c:?}"),
+        }
+    }
+}
+
+impl std::error::Error for FontFormatError {}
+
+impl Font {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{BASELINE_PREFIX}{}\n", self.baseline());
+        for (c, glyph) in self.glyphs() {
+            let c = if c.is_whitespace() || c.is_control() || c == DOT {
+                format!("U+{:04X}", c as u32)
+            } else {
+                c.to_string()
+            };
+            text.push_str(&format!(
+                "{CHAR_PREFIX}{c} {}x{}\n",
+                glyph.width(),
+                glyph.height()
+            ));
+            let bitmap = glyph.bitmap();
+            for row in bitmap.chunks(glyph.width().max(1)).take(glyph.height()) {
+                let row = row
+                    .iter()
+                    .map(|dot| if *dot { DOT } else { ' ' })
+                    .collect::<String>();
+                text.push_str(row.trim_end());
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Font, FontFormatError> {
+        struct PendingGlyph<'a> {
+            c: char,
+            size: Option<(usize, usize)>,
+            /// Line number of the header
+            line: usize,
+            rows: Vec<&'a str>,
+        }
+
+        fn finish(
+            glyph: PendingGlyph<'_>,
+            glyphs: &mut HashMap<char, Glyph>,
+        ) -> Result<(), FontFormatError> {
+            let mut rows = glyph.rows;
+            let (width, height) = match glyph.size {
+                Some(size) => size,
+                None => {
+                    while rows.last().is_some_and(|row| row.trim().is_empty()) {
+                        rows.pop();
+                    }
+                    let width = rows.iter().map(|row| row.chars().count()).max();
+                    (width.unwrap_or(0), rows.len())
+                }
+            };
+            let mut points = Vec::new();
+            for (y, row) in rows.iter().enumerate() {
+                let line = glyph.line + 1 + y;
+                for (x, c) in row.chars().enumerate() {
+                    if c == ' ' {
+                        continue;
+                    }
+                    if c != DOT {
+                        return Err(FontFormatError::InvalidLine(line));
+                    }
+                    if x >= width || y >= height {
+                        return Err(FontFormatError::InvalidLine(line));
+                    }
+                    points.push(PixelPoint {
+                        x: x as isize,
+                        y: y as isize,
+                    });
+                }
+            }
+            if glyphs
+                .insert(glyph.c, Glyph::new(width, height, points))
+                .is_some()
+            {
+                return Err(FontFormatError::DuplicateGlyph(glyph.c));
+            }
+            Ok(())
+        }
+
+        let mut glyphs = HashMap::new();
+        let mut baseline = None;
+        let mut pending: Option<PendingGlyph<'_>> = None;
+        for (i, row) in text.lines().enumerate() {
+            let line = i + 1;
+            if let Some(header) = row.strip_prefix(CHAR_PREFIX) {
+                if let Some(glyph) = pending.take() {
+                    finish(glyph, &mut glyphs)?;
+                }
+                let (c, size) =
+                    parse_char_header(header).ok_or(FontFormatError::InvalidLine(line))?;
+                pending = Some(PendingGlyph {
+                    c,
+                    size,
+                    line,
+                    rows: Vec::new(),
+                });
+            } else if let Some(glyph) = &mut pending {
+                glyph.rows.push(row);
+            } else if let Some(value) = row.strip_prefix(BASELINE_PREFIX) {
+                let value = value.trim().parse();
+                baseline = Some(value.map_err(|_| FontFormatError::InvalidLine(line))?);
+            } else if !row.trim().is_empty() {
+                return Err(FontFormatError::InvalidLine(line));
+            }
+        }
+        if let Some(glyph) = pending.take() {
+            finish(glyph, &mut glyphs)?;
+        }
+
+        let mut font = Font::new(glyphs);
+        if let Some(baseline) = baseline {
+            font.set_baseline(baseline);
+        }
+        Ok(font)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        let glyphs = self.glyphs().collect::<Vec<_>>();
+        push_u32(&mut bytes, self.baseline());
+        push_u32(&mut bytes, glyphs.len());
+        for (c, glyph) in glyphs {
+            push_u32(&mut bytes, c as usize);
+            push_u32(&mut bytes, glyph.width());
+            push_u32(&mut bytes, glyph.height());
+            let mut packed = vec![0; (glyph.width() * glyph.height()).div_ceil(8)];
+            for (i, dot) in glyph.bitmap().into_iter().enumerate() {
+                if dot {
+                    packed[i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+            bytes.extend_from_slice(&packed);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Font, FontFormatError> {
+        let mut reader = ByteReader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(FontFormatError::BadMagic);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(FontFormatError::UnsupportedVersion(version));
+        }
+        let baseline = reader.u32()? as usize;
+        let count = reader.u32()?;
+        let mut glyphs = HashMap::new();
+        for _ in 0..count {
+            let code_point = reader.u32()?;
+            let c =
+                char::from_u32(code_point).ok_or(FontFormatError::InvalidCodePoint(code_point))?;
+            let width = reader.u32()? as usize;
+            let height = reader.u32()? as usize;
+            let dots = width
+                .checked_mul(height)
+                .ok_or(FontFormatError::UnexpectedEnd)?;
+            let packed = reader.take(dots.div_ceil(8))?;
+            let mut points = Vec::new();
+            for i in 0..dots {
+                if packed[i / 8] & (0x80 >> (i % 8)) != 0 {
+                    points.push(PixelPoint {
+                        x: (i % width) as isize,
+                        y: (i / width) as isize,
+                    });
+                }
+            }
+            if glyphs
+                .insert(c, Glyph::new(width, height, points))
+                .is_some()
+            {
+                return Err(FontFormatError::DuplicateGlyph(c));
+            }
+        }
+
+        let mut font = Font::new(glyphs);
+        font.set_baseline(baseline);
+        Ok(font)
+    }
+}
+
+/// Parse `<char> [<width>x<height>]`
+fn parse_char_header(header: &str) -> Option<(char, Option<(usize, usize)>)> {
+    let mut chars = header.chars();
+    let first = chars.next()?;
+    let (c, rest) = match header.strip_prefix("U+") {
+        Some(hex) => {
+            let end = hex.find(' ').unwrap_or(hex.len());
+            let code_point = u32::from_str_radix(&hex[..end], 16).ok()?;
+            (char::from_u32(code_point)?, &hex[end..])
+        }
+        None => (first, chars.as_str()),
+    };
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return Some((c, None));
+    }
+    let (width, height) = rest.split_once('x')?;
+    Some((c, Some((width.parse().ok()?, height.parse().ok()?))))
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("font is too large");
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], FontFormatError> {
+        if self.bytes.len() < n {
+            return Err(FontFormatError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, FontFormatError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::default_font;
+
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let font = default_font();
+        let text = font.to_text();
+        assert_eq!(Font::from_text(&text).unwrap(), font);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let font = default_font();
+        let bytes = font.to_bytes();
+        assert_eq!(Font::from_bytes(&bytes).unwrap(), font);
+        assert_eq!(
+            Font::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(FontFormatError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Font::from_bytes(b"PNG"),
+            Err(FontFormatError::UnexpectedEnd)
+        );
+        assert_eq!(Font::from_bytes(b"PNG\0\0"), Err(FontFormatError::BadMagic));
+    }
+
+    #[test]
+    fn text_without_size() {
+        let text = "
+char A
+ $
+$ $
+$$$
+$ $
+$ $
+
+char .
+
+
+
+$
+";
+        let font = Font::from_text(text).unwrap();
+        let a = font.glyph('A').unwrap();
+        assert_eq!((a.width(), a.height()), (3, 5));
+        let period = font.glyph('.').unwrap();
+        assert_eq!((period.width(), period.height()), (1, 4));
+        assert_eq!(font.baseline(), 5);
+    }
+
+    #[test]
+    fn invalid_text() {
+        assert_eq!(
+            Font::from_text("char A 1x1\n$$\n"),
+            Err(FontFormatError::InvalidLine(2))
+        );
+        assert_eq!(
+            Font::from_text("hello\n"),
+            Err(FontFormatError::InvalidLine(1))
+        );
+        assert_eq!(
+            Font::from_text("char A\n$\nchar A\n$\n"),
+            Err(FontFormatError::DuplicateGlyph('A'))
+        );
+        assert_eq!(
+            Font::from_text("char A\n$#$\n"),
+            Err(FontFormatError::InvalidLine(2))
+        );
+        assert_eq!(
+            Font::from_text("char A\n$\nbaseline 1\n"),
+            Err(FontFormatError::InvalidLine(3))
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use super::{font::Glyph, Font, HeapPixels2D, Pixel, Pixels2D};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub c: char,
+    pub size: usize,
+    /// Synthetic bold
+    pub bold: bool,
+    /// If non-zero, the mask is the ring of this width around the glyph instead of the glyph itself
+    pub outline: usize,
+}
+
+impl GlyphKey {
+    pub fn new(c: char, size: usize) -> GlyphKey {
+        GlyphKey {
+            c,
+            size,
+            bold: false,
+            outline: 0,
+        }
+    }
+}
+
+/// How many pixels synthetic bold widens a glyph of `size`
+pub(crate) fn bold_offset(size: usize) -> usize {
+    size.div_ceil(2)
+}
+
+/// Coverage of a rasterized glyph
+///
+/// `0` is not covered and `u8::MAX` is fully covered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphMask {
+    width: usize,
+    height: usize,
+    coverage: Vec<u8>,
+}
+
+impl GlyphMask {
+    pub fn new(width: usize, height: usize, coverage: Vec<u8>) -> GlyphMask {
+        assert_eq!(width * height, coverage.len());
+        GlyphMask {
+            width,
+            height,
+            coverage,
+        }
+    }
+
+    /// Scale each dot of `glyph` up to a `size` by `size` square
+    pub fn from_glyph(glyph: &Glyph, size: usize) -> GlyphMask {
+        let width = glyph.width() * size;
+        let height = glyph.height() * size;
+        let mut coverage = vec![0; width * height];
+        for p in glyph.points() {
+            let x = p.x as usize * size;
+            let y = p.y as usize * size;
+            for y in y..y + size {
+                coverage[y * width + x..y * width + x + size].fill(u8::MAX);
+            }
+        }
+        GlyphMask::new(width, height, coverage)
+    }
+
+    /// Overlap the mask with a copy of itself shifted right by `offset` pixels
+    #[must_use]
+    pub fn emboldened(&self, offset: usize) -> GlyphMask {
+        let width = self.width + offset;
+        let mut coverage = vec![0; width * self.height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = self.coverage_at(x, y);
+                for dx in [0, offset] {
+                    let i = y * width + x + dx;
+                    coverage[i] = coverage[i].max(c);
+                }
+            }
+        }
+        GlyphMask::new(width, self.height, coverage)
+    }
+
+    /// The ring of `width` pixels around the covered area
+    ///
+    /// The returned mask is `width` pixels larger on each side, so it should be placed `width` pixels up and left of this mask.
+    #[must_use]
+    pub fn outline(&self, width: usize) -> GlyphMask {
+        let w = self.width + 2 * width;
+        let h = self.height + 2 * width;
+        let r = width as isize;
+        let mut coverage = vec![0; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                // Dilate by a disk of radius `width`
+                let mut dilated = 0;
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        if dx * dx + dy * dy > r * r {
+                            continue;
+                        }
+                        let src_x = x as isize + dx - r;
+                        let src_y = y as isize + dy - r;
+                        if let Some(c) = self.get(src_x, src_y) {
+                            dilated = dilated.max(c);
+                        }
+                    }
+                }
+                let inner = self.get(x as isize - r, y as isize - r).unwrap_or(0);
+                coverage[y * w + x] = dilated.saturating_sub(inner);
+            }
+        }
+        GlyphMask::new(w, h, coverage)
+    }
+
+    fn get(&self, x: isize, y: isize) -> Option<u8> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(self.coverage_at(x as usize, y as usize))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn coverage(&self) -> &[u8] {
+        &self.coverage
+    }
+
+    pub fn coverage_at(&self, x: usize, y: usize) -> u8 {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.coverage[y * self.width + x]
+    }
+}
+
+/// Rasterized glyphs of one font
+pub struct GlyphCache<'font> {
+    font: &'font Font,
+    masks: HashMap<GlyphKey, GlyphMask>,
+}
+
+impl<'font> GlyphCache<'font> {
+    pub fn new(font: &'font Font) -> GlyphCache<'font> {
+        GlyphCache {
+            font,
+            masks: HashMap::new(),
+        }
+    }
+
+    pub fn font(&self) -> &'font Font {
+        self.font
+    }
+
+    /// Rasterize the glyph on the first call and return the cached mask afterwards
+    pub fn mask(&mut self, key: GlyphKey) -> &GlyphMask {
+        if !self.masks.contains_key(&key) {
+            let mask = if key.outline > 0 {
+                let fill = GlyphKey { outline: 0, ..key };
+                self.mask(fill).outline(key.outline)
+            } else {
+                let mask = GlyphMask::from_glyph(self.font.glyph_or_unknown(key.c), key.size);
+                if key.bold {
+                    mask.emboldened(bold_offset(key.size))
+                } else {
+                    mask
+                }
+            };
+            self.masks.insert(key, mask);
+        }
+        &self.masks[&key]
+    }
+
+    pub fn len(&self) -> usize {
+        self.masks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.masks.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.masks.clear();
+    }
+
+    /// Pack the glyphs of `chars` at `size` into one image no wider than `max_width`
+    ///
+    /// The atlas is widened to the widest glyph if that doesn't fit in `max_width`.
+    ///
+    /// - Glyphs are packed row by row, tallest first, with one pixel of padding in between.
+    /// - Covered pixels are white with the coverage as the alpha.
+    pub fn atlas(
+        &mut self,
+        chars: impl IntoIterator<Item = char>,
+        size: usize,
+        max_width: usize,
+    ) -> TextAtlas {
+        const PADDING: usize = 1;
+
+        let mut keys = chars
+            .into_iter()
+            .map(|c| GlyphKey::new(c, size))
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|key| key.c);
+        keys.dedup();
+        for key in &keys {
+            self.mask(*key);
+        }
+        keys.sort_by_key(|key| std::cmp::Reverse(self.masks[key].height()));
+        let widest = keys.iter().map(|key| self.masks[key].width()).max();
+        let max_width = max_width.max(widest.unwrap_or(0));
+
+        // Shelf packing
+        let mut entries = HashMap::new();
+        let mut x = 0;
+        let mut y = 0;
+        let mut row_height = 0;
+        let mut width = 0;
+        for key in keys {
+            let mask = &self.masks[&key];
+            if x + mask.width() > max_width {
+                x = 0;
+                y += row_height + PADDING;
+                row_height = 0;
+            }
+            let entry = AtlasEntry {
+                x,
+                y,
+                width: mask.width(),
+                height: mask.height(),
+            };
+            entries.insert(key, entry);
+            x += mask.width() + PADDING;
+            width = width.max(entry.x + entry.width);
+            row_height = row_height.max(mask.height());
+        }
+        let height = y + row_height;
+
+        let mut pixels = HeapPixels2D::new(width, height, Pixel::new(0, 0, 0, 0));
+        let atlas_width = pixels.width();
+        let atlas_pixels = pixels.pixels_mut();
+        for (key, entry) in &entries {
+            let mask = &self.masks[key];
+            for mask_y in 0..mask.height() {
+                for mask_x in 0..mask.width() {
+                    let coverage = mask.coverage_at(mask_x, mask_y);
+                    let i = (entry.y + mask_y) * atlas_width + entry.x + mask_x;
+                    atlas_pixels[i] = Pixel::new(u8::MAX, u8::MAX, u8::MAX, coverage);
+                }
+            }
+        }
+
+        TextAtlas { pixels, entries }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasEntry {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextAtlas {
+    pixels: HeapPixels2D,
+    entries: HashMap<GlyphKey, AtlasEntry>,
+}
+
+impl TextAtlas {
+    pub fn pixels(&self) -> &HeapPixels2D {
+        &self.pixels
+    }
+
+    pub fn entry(&self, key: GlyphKey) -> Option<&AtlasEntry> {
+        self.entries.get(&key)
+    }
+
+    pub fn entries(&self) -> &HashMap<GlyphKey, AtlasEntry> {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::default_font;
+
+    use super::*;
+
+    #[test]
+    fn mask_from_glyph() {
+        let font = default_font();
+        let mask = GlyphMask::from_glyph(font.glyph('A').unwrap(), 2);
+        assert_eq!(mask.width(), 6);
+        assert_eq!(mask.height(), 10);
+        assert_eq!(mask.coverage_at(0, 0), 0);
+        assert_eq!(mask.coverage_at(2, 0), u8::MAX);
+        assert_eq!(mask.coverage_at(3, 1), u8::MAX);
+        assert_eq!(mask.coverage_at(4, 0), 0);
+    }
+
+    #[test]
+    fn cache_reuses_masks() {
+        let font = default_font();
+        let mut cache = GlyphCache::new(&font);
+        cache.mask(GlyphKey::new('A', 1));
+        cache.mask(GlyphKey::new('A', 1));
+        cache.mask(GlyphKey::new('A', 2));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn bold_and_outline() {
+        let mask = GlyphMask::new(1, 1, vec![u8::MAX]);
+        let bold = mask.emboldened(2);
+        assert_eq!(bold.coverage(), [u8::MAX, 0, u8::MAX]);
+
+        let outline = mask.outline(1);
+        #[rustfmt::skip]
+        assert_eq!(outline.coverage(), [
+            0, u8::MAX, 0,
+            u8::MAX, 0, u8::MAX,
+            0, u8::MAX, 0,
+        ]);
+    }
+
+    #[test]
+    fn atlas() {
+        let font = default_font();
+        let mut cache = GlyphCache::new(&font);
+        let atlas = cache.atlas("Hello, world!".chars(), 2, 32);
+        assert!(atlas.pixels().width() <= 32);
+        assert_eq!(atlas.entries().len(), 10);
+
+        let entries = atlas.entries().values().collect::<Vec<_>>();
+        for (i, a) in entries.iter().enumerate() {
+            for b in &entries[i + 1..] {
+                let overlaps_x = a.x < b.x + b.width && b.x < a.x + a.width;
+                let overlaps_y = a.y < b.y + b.height && b.y < a.y + a.height;
+                assert!(!(overlaps_x && overlaps_y));
+            }
+        }
+
+        let key = GlyphKey::new('w', 2);
+        let entry = atlas.entry(key).unwrap();
+        let mask = cache.mask(key).clone();
+        for y in 0..entry.height {
+            for x in 0..entry.width {
+                let i = (entry.y + y) * atlas.pixels().width() + entry.x + x;
+                assert_eq!(atlas.pixels().pixels()[i].a(), mask.coverage_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn atlas_narrower_than_glyph() {
+        let font = default_font();
+        let mut cache = GlyphCache::new(&font);
+        let width = "WM"
+            .chars()
+            .map(|c| cache.mask(GlyphKey::new(c, 4)).width())
+            .max();
+        let atlas = cache.atlas("WM".chars(), 4, 1);
+        assert_eq!(Some(atlas.pixels().width()), width);
+        assert_eq!(atlas.entries().len(), 2);
+    }
+}
@@ -0,0 +1,245 @@
+use super::{Canvas, HeapPixels2D, Pixel, Pixels2D};
+
+/// How a layer's colors combine with the pixels under it
+///
+/// - Ref: <https://www.w3.org/TR/compositing-1/#blending>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    /// Sum of both colors, clamped to white
+    Add,
+}
+
+impl BlendMode {
+    /// Blend one channel of `source` onto `backdrop`, both in `0..=1`
+    fn blend(&self, backdrop: f64, source: f64) -> f64 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Overlay => {
+                if backdrop <= 0.5 {
+                    2. * backdrop * source
+                } else {
+                    1. - 2. * (1. - backdrop) * (1. - source)
+                }
+            }
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::Add => (backdrop + source).min(1.),
+        }
+    }
+}
+
+/// How [`Canvas::pop_layer`] composites a layer back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerOptions<'mask> {
+    /// Applied once to the whole layer, so overlapping shapes inside it don't show through each other
+    pub opacity: f64,
+    pub blend: BlendMode,
+    /// Its alpha channel scales the layer's alpha; pixels outside of it are hidden
+    pub mask: Option<&'mask HeapPixels2D>,
+}
+
+impl Default for LayerOptions<'_> {
+    fn default() -> Self {
+        Self {
+            opacity: 1.,
+            blend: BlendMode::Normal,
+            mask: None,
+        }
+    }
+}
+
+impl<P> Canvas<'_, P>
+where
+    P: Pixels2D,
+{
+    /// A transparent offscreen layer the size of this canvas
+    pub fn push_layer(&self) -> HeapPixels2D {
+        HeapPixels2D::new(self.width(), self.height(), Pixel::new(0, 0, 0, 0))
+    }
+
+    /// Composite `layer` onto this canvas with its top-left at the canvas origin
+    ///
+    /// Only pixels the layer covers are written and marked dirty.
+    pub fn pop_layer(&mut self, layer: &HeapPixels2D, options: &LayerOptions<'_>) {
+        let opacity = options.opacity.clamp(0., 1.);
+        let width = layer.width().min(self.width());
+        let height = layer.height().min(self.height());
+        for y in 0..height {
+            for x in 0..width {
+                let source = layer.pixels()[y * layer.width() + x];
+                let mask = match options.mask {
+                    Some(mask) if x < mask.width() && y < mask.height() => {
+                        mask.pixels()[y * mask.width() + x].a() as f64 / u8::MAX as f64
+                    }
+                    Some(_) => 0.,
+                    None => 1.,
+                };
+                let alpha = (source.a() as f64 * opacity * mask).round() as u8;
+                if alpha == 0 {
+                    continue;
+                }
+                let p = self.pixel_mut(x, y);
+                *p = blend(source, alpha, *p, options.blend).over(*p);
+            }
+        }
+    }
+
+    /// Draw into a fresh layer with `draw`, then [`Canvas::pop_layer`] it
+    ///
+    /// `draw` is not called if this canvas is empty.
+    pub fn with_layer(
+        &mut self,
+        options: &LayerOptions<'_>,
+        draw: impl FnOnce(&mut Canvas<'_, HeapPixels2D>),
+    ) {
+        let mut layer = self.push_layer();
+        if layer.width() == 0 || layer.height() == 0 {
+            return;
+        }
+        draw(&mut Canvas::new_entire(&mut layer));
+        self.pop_layer(&layer, options);
+    }
+}
+
+/// `source` with its colors mixed with the blended ones by the backdrop's alpha, ready to go over the backdrop
+fn blend(source: Pixel, alpha: u8, backdrop: Pixel, mode: BlendMode) -> Pixel {
+    let backdrop_alpha = backdrop.a() as f64 / u8::MAX as f64;
+    let channel = |b: u8, s: u8| {
+        let (b, s) = (b as f64 / u8::MAX as f64, s as f64 / u8::MAX as f64);
+        let mixed = (1. - backdrop_alpha) * s + backdrop_alpha * mode.blend(b, s);
+        (mixed * u8::MAX as f64).round() as u8
+    };
+    Pixel::new(
+        channel(backdrop.r(), source.r()),
+        channel(backdrop.g(), source.g()),
+        channel(backdrop.b(), source.b()),
+        alpha,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DirtyRect, PixelPoint};
+
+    use super::*;
+
+    const BACKGROUND: Pixel = Pixel::new(0xff, 0xff, 0xff, 0xff);
+    const HALF_RED: Pixel = Pixel::new(0xff, 0, 0, 0x80);
+
+    fn pixel(pixels: &HeapPixels2D, x: usize, y: usize) -> Pixel {
+        pixels.pixels()[y * pixels.width() + x]
+    }
+
+    #[test]
+    fn group_opacity() {
+        let mut direct = HeapPixels2D::new(8, 8, BACKGROUND);
+        let mut canvas = Canvas::new_entire(&mut direct);
+        canvas.fill_pixel_rect(PixelPoint { x: 0, y: 0 }, 6, 6, HALF_RED);
+        canvas.fill_pixel_rect(PixelPoint { x: 2, y: 2 }, 6, 6, HALF_RED);
+        // The overlap is darker when drawn directly
+        assert_ne!(pixel(&direct, 1, 1), pixel(&direct, 3, 3));
+
+        let mut grouped = HeapPixels2D::new(8, 8, BACKGROUND);
+        let mut canvas = Canvas::new_entire(&mut grouped);
+        let options = LayerOptions {
+            opacity: 0x80 as f64 / 0xff as f64,
+            ..Default::default()
+        };
+        canvas.with_layer(&options, |layer| {
+            let red = Pixel::new(0xff, 0, 0, 0xff);
+            layer.fill_pixel_rect(PixelPoint { x: 0, y: 0 }, 6, 6, red);
+            layer.fill_pixel_rect(PixelPoint { x: 2, y: 2 }, 6, 6, red);
+        });
+        assert_eq!(canvas.dirty_rect(), DirtyRect::new(0, 0, 8, 8));
+        assert_eq!(pixel(&grouped, 1, 1), pixel(&grouped, 3, 3));
+        assert_eq!(pixel(&grouped, 1, 1), pixel(&direct, 1, 1));
+        assert_eq!(pixel(&grouped, 7, 0), BACKGROUND);
+    }
+
+    #[test]
+    fn blend_modes() {
+        let gray = Pixel::new(0x80, 0x80, 0x80, 0xff);
+        let source = Pixel::new(0xff, 0x80, 0, 0xff);
+        let composite = |blend| {
+            let mut pixels = HeapPixels2D::new(1, 1, gray);
+            let mut canvas = Canvas::new_entire(&mut pixels);
+            let mut layer = canvas.push_layer();
+            *Canvas::new_entire(&mut layer).pixel_mut(0, 0) = source;
+            canvas.pop_layer(
+                &layer,
+                &LayerOptions {
+                    blend,
+                    ..Default::default()
+                },
+            );
+            pixels.pixels()[0]
+        };
+        assert_eq!(composite(BlendMode::Normal), source);
+        assert_eq!(
+            composite(BlendMode::Multiply),
+            Pixel::new(0x80, 0x40, 0, 0xff)
+        );
+        assert_eq!(
+            composite(BlendMode::Screen),
+            Pixel::new(0xff, 0xc0, 0x80, 0xff)
+        );
+        // The gray backdrop is just above half, so the channels are screened
+        assert_eq!(
+            composite(BlendMode::Overlay),
+            Pixel::new(0xff, 0x80, 0x01, 0xff)
+        );
+        assert_eq!(
+            composite(BlendMode::Darken),
+            Pixel::new(0x80, 0x80, 0, 0xff)
+        );
+        assert_eq!(
+            composite(BlendMode::Lighten),
+            Pixel::new(0xff, 0x80, 0x80, 0xff)
+        );
+        assert_eq!(
+            composite(BlendMode::Add),
+            Pixel::new(0xff, 0xff, 0x80, 0xff)
+        );
+    }
+
+    #[test]
+    fn mask() {
+        let mut pixels = HeapPixels2D::new(4, 1, BACKGROUND);
+        let mut mask = HeapPixels2D::new(3, 1, Pixel::new(0, 0, 0, 0));
+        mask.pixels_mut()[1] = Pixel::new(0, 0, 0, 0xff);
+        let mut canvas = Canvas::new_entire(&mut pixels);
+        let options = LayerOptions {
+            mask: Some(&mask),
+            ..Default::default()
+        };
+        canvas.with_layer(&options, |layer| layer.fill(Pixel::new(0, 0, 0xff, 0xff)));
+        assert_eq!(canvas.dirty_rect(), DirtyRect::new(1, 0, 1, 1));
+        assert_eq!(
+            pixels.pixels(),
+            [
+                BACKGROUND,
+                Pixel::new(0, 0, 0xff, 0xff),
+                BACKGROUND,
+                BACKGROUND
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_canvas() {
+        let mut pixels = HeapPixels2D::new(2, 2, BACKGROUND);
+        let mut canvas = Canvas::new(&mut pixels, 1..1, 0..2);
+        let mut drawn = false;
+        canvas.with_layer(&LayerOptions::default(), |_| drawn = true);
+        assert!(!drawn);
+    }
+}
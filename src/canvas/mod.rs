@@ -1,26 +1,44 @@
 use std::cmp::Ordering;
 
+mod dirty_rect;
+mod display_list;
+mod error;
 mod float_point;
 mod font;
+mod font_format;
+mod glyph_cache;
+mod layer;
 mod pixel;
 mod pixel_point;
+mod rich_text;
+mod text;
 
-use crate::{canvas::font::unknown_glyph, math};
+use crate::math;
 
 pub use self::{
+    dirty_rect::DirtyRect,
+    display_list::{DisplayList, DrawCommand},
+    error::CanvasError,
     float_point::{FloatPoint, FloatSpace},
-    font::{default_font, Font},
+    font::{default_font, Font, Glyph},
+    font_format::FontFormatError,
+    glyph_cache::{AtlasEntry, GlyphCache, GlyphKey, GlyphMask, TextAtlas},
+    layer::{BlendMode, LayerOptions},
     pixel::{HeapPixels2D, Pixel, Pixels2D, StackPixels2D, BLACK, BLUE, GREEN, RED, WHITE},
     pixel_point::{EvenF, PixelPoint, PixelPointF},
+    rich_text::{rich_text_size, TextSpan},
+    text::{TextOutline, TextShadow, TextStyle},
 };
 
-const RESOLUTION: usize = 2;
+pub(crate) const RESOLUTION: usize = 2;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Canvas<'pixels, P> {
     pixels2d: &'pixels mut P,
     x_range: std::ops::Range<usize>,
     y_range: std::ops::Range<usize>,
+    /// Empty until something is drawn
+    dirty: DirtyRect,
 }
 
 impl<'pixels, P> Canvas<'pixels, P>
@@ -32,20 +50,39 @@ where
         let height = pixels2d.height();
         Self::new(pixels2d, 0..width, 0..height)
     }
+    /// Panics if the ranges are out of `pixels2d`
     pub fn new(
         pixels2d: &'pixels mut P,
         x_range: std::ops::Range<usize>,
         y_range: std::ops::Range<usize>,
     ) -> Self {
-        assert!(x_range.start < pixels2d.width());
-        assert!(x_range.end <= pixels2d.width());
-        assert!(y_range.start < pixels2d.height());
-        assert!(y_range.end <= pixels2d.height());
-        Self {
+        Self::try_new(pixels2d, x_range, y_range).unwrap_or_else(|e| panic!("{e}"))
+    }
+    pub fn try_new(
+        pixels2d: &'pixels mut P,
+        x_range: std::ops::Range<usize>,
+        y_range: std::ops::Range<usize>,
+    ) -> Result<Self, CanvasError> {
+        let width = pixels2d.width();
+        let height = pixels2d.height();
+        if x_range.start >= width || x_range.end > width {
+            return Err(CanvasError::XRangeOutOfBounds {
+                range: x_range,
+                width,
+            });
+        }
+        if y_range.start >= height || y_range.end > height {
+            return Err(CanvasError::YRangeOutOfBounds {
+                range: y_range,
+                height,
+            });
+        }
+        Ok(Self {
             pixels2d,
             x_range,
             y_range,
-        }
+            dirty: DirtyRect::default(),
+        })
     }
 
     pub fn width(&self) -> usize {
@@ -59,13 +96,41 @@ where
         self.pixels2d
     }
 
+    /// Panics if `(x, y)` is out of the canvas
     pub fn pixel_mut(&mut self, x: usize, y: usize) -> &mut Pixel {
-        assert!(x < self.x_range.len());
-        assert!(y < self.y_range.len());
+        self.try_pixel_mut(x, y).unwrap_or_else(|e| panic!("{e}"))
+    }
+    pub fn try_pixel_mut(&mut self, x: usize, y: usize) -> Result<&mut Pixel, CanvasError> {
+        if x >= self.width() || y >= self.height() {
+            return Err(CanvasError::PixelOutOfBounds {
+                x,
+                y,
+                width: self.width(),
+                height: self.height(),
+            });
+        }
         let x = x + self.x_range.start;
         let y = y + self.y_range.start;
+        self.dirty.include(x, y);
         let w = self.pixels2d.width();
-        &mut self.pixels2d.pixels_mut()[y * w + x]
+        Ok(&mut self.pixels2d.pixels_mut()[y * w + x])
+    }
+
+    /// The bounds of all pixels written through this canvas, in coordinates of the underlying pixels
+    ///
+    /// Renderers can keep it across frames to redraw or upload only what changed.
+    pub fn dirty_rect(&self) -> DirtyRect {
+        self.dirty
+    }
+
+    /// Return [`Canvas::dirty_rect`] and start over with an empty one
+    pub fn take_dirty_rect(&mut self) -> DirtyRect {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Add a region changed without this canvas, in coordinates of the underlying pixels
+    pub fn mark_dirty(&mut self, rect: DirtyRect) {
+        self.dirty = self.dirty.union(&rect);
     }
 
     pub fn pixel_over_by(&mut self, x: usize, y: usize, color: Pixel) {
@@ -74,6 +139,8 @@ where
     }
 
     pub fn fill(&mut self, pixel: Pixel) {
+        let (width, height) = (self.pixels2d.width(), self.pixels2d.height());
+        self.mark_dirty(DirtyRect::new(0, 0, width, height));
         self.pixels2d.pixels_mut().fill(pixel);
     }
 
@@ -319,56 +386,6 @@ where
         }
     }
 
-    pub fn pixel_text(
-        &mut self,
-        text: &str,
-        pos: PixelPoint,
-        font: &Font,
-        size: usize,
-        color: Pixel,
-    ) {
-        assert!(size <= isize::MAX as usize, "size is too big");
-        let unknown_glyph = unknown_glyph();
-        let mut x = pos.x;
-        let mut max_height: usize = 0;
-        let mut y = pos.y;
-        for c in text.chars() {
-            if c == '\n' {
-                x = pos.x;
-                let dy = (max_height + 1) * size;
-                assert!(dy <= isize::MAX as usize, "dy is too big");
-                y += dy as isize;
-                max_height = 0;
-                continue;
-            }
-
-            let glyph = match font.glyph(c) {
-                Some(glyph) => glyph,
-                None => &unknown_glyph,
-            };
-
-            for p in glyph.points() {
-                for y_i in 0..size {
-                    let y = y + p.y * size as isize + y_i as isize;
-                    max_height = max_height.max(glyph.height() + 1);
-                    if y < 0 || y >= self.height() as isize {
-                        continue;
-                    }
-                    for x_i in 0..size {
-                        let x = x + p.x * size as isize + x_i as isize;
-                        if x < 0 || x >= self.width() as isize {
-                            continue;
-                        }
-                        self.pixel_over_by(x as usize, y as usize, color);
-                    }
-                }
-            }
-            let dx = (glyph.width() + 1) * size;
-            assert!(dx <= isize::MAX as usize, "dx is too big");
-            x += dx as isize;
-        }
-    }
-
     fn set_anti_aliasing_pixel(
         &mut self,
         x: usize,
@@ -448,7 +465,7 @@ fn trim_edge(e: isize) -> isize {
 /// There is an offset from the middle point of the original line to the middle point of each segment.
 ///
 /// It iterates through all the offsets.
-fn offset_from_middle_iter() -> impl Iterator<Item = f64> {
+pub(crate) fn offset_from_middle_iter() -> impl Iterator<Item = f64> {
     /// Say we have a line of size 1 and we divided it into `res` segments evenly.
     ///
     /// The middle of the line is at 0.5.
@@ -1,3 +1,5 @@
+use super::{error::check_size, CanvasError};
+
 pub trait Pixels2D {
     fn width(&self) -> usize;
     fn height(&self) -> usize;
@@ -13,13 +15,22 @@ pub struct StackPixels2D<const N: usize> {
 }
 
 impl<const N: usize> StackPixels2D<N> {
+    /// Panics if `width * height` is not `N`
     pub fn new(width: usize, height: usize, fill: Pixel) -> StackPixels2D<N> {
-        assert_eq!(width * height, N);
-        StackPixels2D {
+        Self::try_new(width, height, fill).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_new(
+        width: usize,
+        height: usize,
+        fill: Pixel,
+    ) -> Result<StackPixels2D<N>, CanvasError> {
+        check_size(width, height, N)?;
+        Ok(StackPixels2D {
             pixels: [fill; N],
             width,
             height,
-        }
+        })
     }
 }
 
@@ -56,6 +67,26 @@ impl HeapPixels2D {
             height,
         }
     }
+
+    /// `pixels` are in rows from top to bottom.
+    ///
+    /// Panics if `width * height` is not the number of pixels.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Pixel>) -> HeapPixels2D {
+        Self::try_from_pixels(width, height, pixels).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_from_pixels(
+        width: usize,
+        height: usize,
+        pixels: Vec<Pixel>,
+    ) -> Result<HeapPixels2D, CanvasError> {
+        check_size(width, height, pixels.len())?;
+        Ok(HeapPixels2D {
+            pixels,
+            width,
+            height,
+        })
+    }
 }
 
 impl Pixels2D for HeapPixels2D {
@@ -172,4 +203,19 @@ mod tests {
         assert_eq!(pixel.a(), 0x44);
         assert_eq!(pixel.to_u32(), 0x44332211);
     }
+
+    #[test]
+    fn try_new() {
+        let fill = Pixel::new(0, 0, 0, 0);
+        assert!(StackPixels2D::<6>::try_new(2, 3, fill).is_ok());
+        assert_eq!(
+            StackPixels2D::<6>::try_new(2, 2, fill),
+            Err(CanvasError::SizeMismatch {
+                width: 2,
+                height: 2,
+                len: 6
+            })
+        );
+        assert!(HeapPixels2D::try_from_pixels(usize::MAX, 2, vec![fill; 2]).is_err());
+    }
 }
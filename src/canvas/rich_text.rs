@@ -0,0 +1,522 @@
+use super::{
+    glyph_cache::{bold_offset, GlyphCache, GlyphKey, GlyphMask},
+    Canvas, Font, Pixel, PixelPoint, Pixels2D, TextStyle,
+};
+
+/// A run of text sharing the same font, size, color and style
+#[derive(Clone, Copy)]
+pub struct TextSpan<'a> {
+    pub text: &'a str,
+    pub font: &'a Font,
+    pub size: usize,
+    pub color: Pixel,
+    pub style: TextStyle,
+}
+
+impl<'a> TextSpan<'a> {
+    pub fn new(text: &'a str, font: &'a Font, size: usize, color: Pixel) -> TextSpan<'a> {
+        TextSpan {
+            text,
+            font,
+            size,
+            color,
+            style: TextStyle::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_style(mut self, style: TextStyle) -> TextSpan<'a> {
+        self.style = style;
+        self
+    }
+
+    fn bold_offset(&self) -> usize {
+        if self.style.bold {
+            bold_offset(self.size)
+        } else {
+            0
+        }
+    }
+
+    /// Width of the drawn part of the glyph
+    fn ink_width(&self, c: char) -> usize {
+        self.font.glyph_or_unknown(c).width() * self.size + self.bold_offset()
+    }
+
+    /// Distance from the start of this glyph to the start of the next glyph
+    fn advance(&self, c: char) -> usize {
+        (self.font.glyph_or_unknown(c).width() + 1) * self.size + self.bold_offset()
+    }
+}
+
+/// Width and height in pixels of the laid out spans
+pub fn rich_text_size(spans: &[TextSpan<'_>], max_width: Option<usize>) -> (usize, usize) {
+    let layout = RichTextLayout::new(spans, max_width);
+    (layout.width, layout.height)
+}
+
+impl<'pixels, P> Canvas<'pixels, P>
+where
+    P: Pixels2D,
+{
+    /// Lay out `spans` as one flow of text starting at `pos`.
+    ///
+    /// - Glyphs of different spans on the same line share the same baseline.
+    /// - If `max_width` is set, lines are wrapped between words to fit in it.
+    /// - Layers are drawn from bottom to top across all spans: shadow, outline, glyphs, underline and strikethrough.
+    pub fn pixel_rich_text(
+        &mut self,
+        spans: &[TextSpan<'_>],
+        pos: PixelPoint,
+        max_width: Option<usize>,
+    ) {
+        let mut caches = Vec::new();
+        self.pixel_spans(spans, pos, max_width, &mut caches);
+    }
+
+    pub(crate) fn pixel_spans<'a>(
+        &mut self,
+        spans: &[TextSpan<'a>],
+        pos: PixelPoint,
+        max_width: Option<usize>,
+        masks: &mut impl MaskSource<'a>,
+    ) {
+        let layout = RichTextLayout::new(spans, max_width);
+
+        for (i, span) in spans.iter().enumerate() {
+            let Some(shadow) = span.style.shadow else {
+                continue;
+            };
+            let pos = PixelPoint {
+                x: pos.x + shadow.offset.x,
+                y: pos.y + shadow.offset.y,
+            };
+            self.pixel_span_shadow(spans, &layout, i, pos, shadow.color, masks);
+        }
+        for (i, span) in spans.iter().enumerate() {
+            let outline_color = span.style.outline.map(|outline| outline.color);
+            let Some(outline_color) = outline_color else {
+                continue;
+            };
+            self.pixel_span_outline(spans, &layout, i, pos, outline_color, masks);
+        }
+        for (i, span) in spans.iter().enumerate() {
+            self.pixel_span_glyphs(spans, &layout, i, pos, span.color, masks);
+        }
+        for (i, span) in spans.iter().enumerate() {
+            self.pixel_span_decorations(spans, &layout, i, pos, span.color);
+        }
+    }
+
+    /// Draw the outline, glyphs and decorations of the span as one mask
+    ///
+    /// Composited once, so a translucent `color` is even where the layers overlap.
+    fn pixel_span_shadow<'a>(
+        &mut self,
+        spans: &[TextSpan<'a>],
+        layout: &RichTextLayout,
+        span: usize,
+        pos: PixelPoint,
+        color: Pixel,
+        masks: &mut impl MaskSource<'a>,
+    ) {
+        let s = &spans[span];
+        let outline = s.style.outline.map_or(0, |outline| outline.width);
+        let w = outline as isize;
+        let origin = PixelPoint { x: 0, y: 0 };
+
+        let mut layers: Vec<(PixelPoint, GlyphMask)> = Vec::new();
+        for glyph in layout.glyphs.iter().filter(|glyph| glyph.span == span) {
+            let p = layout.glyph_pos(spans, glyph, origin);
+            let key = GlyphKey {
+                bold: s.style.bold,
+                ..GlyphKey::new(glyph.c, s.size)
+            };
+            layers.push((p, masks.mask(s.font, key).clone()));
+            if outline > 0 {
+                let p = PixelPoint {
+                    x: p.x - w,
+                    y: p.y - w,
+                };
+                layers.push((p, masks.mask(s.font, GlyphKey { outline, ..key }).clone()));
+            }
+        }
+        let rects = layout.decoration_rects(spans, span);
+
+        let bounds = layers
+            .iter()
+            .map(|(p, mask)| (*p, mask.width() as isize, mask.height() as isize))
+            .chain(rects.iter().copied())
+            .filter(|(_, w, h)| *w > 0 && *h > 0);
+        let mut min = PixelPoint {
+            x: isize::MAX,
+            y: isize::MAX,
+        };
+        let mut max = PixelPoint {
+            x: isize::MIN,
+            y: isize::MIN,
+        };
+        for (p, w, h) in bounds {
+            min = PixelPoint {
+                x: min.x.min(p.x),
+                y: min.y.min(p.y),
+            };
+            max = PixelPoint {
+                x: max.x.max(p.x + w),
+                y: max.y.max(p.y + h),
+            };
+        }
+        if min.x >= max.x || min.y >= max.y {
+            return;
+        }
+
+        let width = (max.x - min.x) as usize;
+        let height = (max.y - min.y) as usize;
+        let mut coverage = vec![0; width * height];
+        let mut cover = |x: isize, y: isize, value: u8| {
+            let i = (y - min.y) as usize * width + (x - min.x) as usize;
+            coverage[i] = coverage[i].max(value);
+        };
+        for (p, mask) in &layers {
+            for y in 0..mask.height() {
+                for x in 0..mask.width() {
+                    let value = mask.coverage_at(x, y);
+                    cover(p.x + x as isize, p.y + y as isize, value);
+                }
+            }
+        }
+        for (p, w, h) in &rects {
+            for y in p.y..p.y + h {
+                for x in p.x..p.x + w {
+                    cover(x, y, u8::MAX);
+                }
+            }
+        }
+
+        let p = PixelPoint {
+            x: pos.x + min.x,
+            y: pos.y + min.y,
+        };
+        self.fill_pixel_mask(&GlyphMask::new(width, height, coverage), p, color);
+    }
+
+    fn pixel_span_outline<'a>(
+        &mut self,
+        spans: &[TextSpan<'a>],
+        layout: &RichTextLayout,
+        span: usize,
+        pos: PixelPoint,
+        color: Pixel,
+        masks: &mut impl MaskSource<'a>,
+    ) {
+        let s = &spans[span];
+        let Some(outline) = s.style.outline.filter(|outline| outline.width > 0) else {
+            return;
+        };
+        let w = outline.width as isize;
+        for glyph in layout.glyphs.iter().filter(|glyph| glyph.span == span) {
+            let p = layout.glyph_pos(spans, glyph, pos);
+            let p = PixelPoint {
+                x: p.x - w,
+                y: p.y - w,
+            };
+            let key = GlyphKey {
+                c: glyph.c,
+                size: s.size,
+                bold: s.style.bold,
+                outline: outline.width,
+            };
+            self.fill_pixel_mask(masks.mask(s.font, key), p, color);
+        }
+    }
+
+    fn pixel_span_glyphs<'a>(
+        &mut self,
+        spans: &[TextSpan<'a>],
+        layout: &RichTextLayout,
+        span: usize,
+        pos: PixelPoint,
+        color: Pixel,
+        masks: &mut impl MaskSource<'a>,
+    ) {
+        let s = &spans[span];
+        for glyph in layout.glyphs.iter().filter(|glyph| glyph.span == span) {
+            let p = layout.glyph_pos(spans, glyph, pos);
+            let key = GlyphKey {
+                bold: s.style.bold,
+                ..GlyphKey::new(glyph.c, s.size)
+            };
+            self.fill_pixel_mask(masks.mask(s.font, key), p, color);
+        }
+    }
+
+    fn pixel_span_decorations(
+        &mut self,
+        spans: &[TextSpan<'_>],
+        layout: &RichTextLayout,
+        span: usize,
+        pos: PixelPoint,
+        color: Pixel,
+    ) {
+        for (p, w, h) in layout.decoration_rects(spans, span) {
+            let p = PixelPoint {
+                x: pos.x + p.x,
+                y: pos.y + p.y,
+            };
+            self.fill_pixel_rect(p, w, h, color);
+        }
+    }
+}
+
+/// Where the glyph masks of each font come from
+pub(crate) trait MaskSource<'a> {
+    fn mask(&mut self, font: &'a Font, key: GlyphKey) -> &GlyphMask;
+}
+
+/// A cache for each font
+impl<'a> MaskSource<'a> for Vec<GlyphCache<'a>> {
+    fn mask(&mut self, font: &'a Font, key: GlyphKey) -> &GlyphMask {
+        let i = match self
+            .iter()
+            .position(|cache| std::ptr::eq(cache.font(), font))
+        {
+            Some(i) => i,
+            None => {
+                self.push(GlyphCache::new(font));
+                self.len() - 1
+            }
+        };
+        self[i].mask(key)
+    }
+}
+
+/// All spans are assumed to be in the font of the cache
+impl<'a> MaskSource<'a> for GlyphCache<'_> {
+    fn mask(&mut self, _font: &'a Font, key: GlyphKey) -> &GlyphMask {
+        GlyphCache::mask(self, key)
+    }
+}
+
+struct PlacedGlyph {
+    span: usize,
+    c: char,
+    /// Relative to the start of the line
+    x: isize,
+    line: usize,
+}
+
+struct RichLine {
+    /// Relative to the top of the text
+    baseline: isize,
+}
+
+struct RichTextLayout {
+    glyphs: Vec<PlacedGlyph>,
+    lines: Vec<RichLine>,
+    width: usize,
+    height: usize,
+}
+
+impl RichTextLayout {
+    pub fn new(spans: &[TextSpan<'_>], max_width: Option<usize>) -> Self {
+        let chars = spans
+            .iter()
+            .enumerate()
+            .flat_map(|(i, span)| span.text.chars().map(move |c| (i, c)))
+            .collect::<Vec<_>>();
+
+        let mut glyphs = Vec::new();
+        // Minimum height of each line, which only matters when no glyph of the line is drawn
+        let mut min_heights = vec![0];
+        let mut x: isize = 0;
+        // The line was started by wrapping instead of a line break
+        let mut wrapped = false;
+        let fits = |x: isize, width: usize| match max_width {
+            Some(max_width) => x == 0 || x + width as isize <= max_width as isize,
+            None => true,
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            let (span, c) = chars[i];
+            if c == '\n' {
+                let line = min_heights.len() - 1;
+                min_heights[line] = min_heights[line].max(spans[span].size);
+                min_heights.push(0);
+                x = 0;
+                wrapped = false;
+                i += 1;
+                continue;
+            }
+
+            // A run of either whitespace or a word
+            let is_space = c.is_whitespace();
+            let len = chars[i..]
+                .iter()
+                .take_while(|(_, c)| *c != '\n' && c.is_whitespace() == is_space)
+                .count();
+            let run = &chars[i..i + len];
+            i += len;
+
+            if is_space && wrapped && x == 0 {
+                // Drop the spaces the line was wrapped at
+                continue;
+            }
+            if !is_space {
+                let (last_span, last_c) = run[len - 1];
+                let width = run
+                    .iter()
+                    .map(|(span, c)| spans[*span].advance(*c))
+                    .sum::<usize>()
+                    - spans[last_span].advance(last_c)
+                    + spans[last_span].ink_width(last_c);
+                if !fits(x, width) {
+                    min_heights.push(0);
+                    x = 0;
+                    wrapped = true;
+                }
+            }
+            for &(span, c) in run {
+                let s = &spans[span];
+                // Break the word if it is longer than a line
+                if !is_space && !fits(x, s.ink_width(c)) {
+                    min_heights.push(0);
+                    x = 0;
+                    wrapped = true;
+                }
+                let line = min_heights.len() - 1;
+                min_heights[line] = min_heights[line].max(s.size);
+                glyphs.push(PlacedGlyph { span, c, x, line });
+                x += s.advance(c) as isize;
+            }
+        }
+
+        // Line metrics
+        let line_count = min_heights.len();
+        let mut ascents = vec![None; line_count];
+        let mut descents = vec![0; line_count];
+        let mut width = 0;
+        for glyph in &glyphs {
+            let s = &spans[glyph.span];
+            let g = s.font.glyph_or_unknown(glyph.c);
+            if g.points().is_empty() {
+                continue;
+            }
+            width = width.max(glyph.x + s.ink_width(glyph.c) as isize);
+            let ascent = s.font.baseline() * s.size;
+            // Same line spacing as `Canvas::pixel_text`
+            let descent = ((g.height() + 2) * s.size).saturating_sub(ascent);
+            let line_ascent = ascents[glyph.line].get_or_insert(0);
+            *line_ascent = ascent.max(*line_ascent);
+            descents[glyph.line] = descent.max(descents[glyph.line]);
+        }
+        let mut lines = Vec::with_capacity(line_count);
+        let mut top = 0;
+        for line in 0..line_count {
+            let (ascent, height) = match ascents[line] {
+                Some(ascent) => (ascent, ascent + descents[line]),
+                None => (0, min_heights[line]),
+            };
+            lines.push(RichLine {
+                baseline: (top + ascent) as isize,
+            });
+            top += height;
+        }
+
+        Self {
+            glyphs,
+            lines,
+            width: width as usize,
+            height: top,
+        }
+    }
+
+    /// Underlines and strikethroughs of the span as `(top_left, width, height)` relative to the top of the text
+    fn decoration_rects(
+        &self,
+        spans: &[TextSpan<'_>],
+        span: usize,
+    ) -> Vec<(PixelPoint, isize, isize)> {
+        let s = &spans[span];
+        if !s.style.underline && !s.style.strikethrough {
+            return Vec::new();
+        }
+
+        // Runs of consecutive glyphs of the span on the same line as `(line, x_start, x_end)`
+        let mut runs: Vec<(usize, isize, isize)> = Vec::new();
+        let mut prev: Option<usize> = None;
+        for (i, glyph) in self.glyphs.iter().enumerate() {
+            if glyph.span != span {
+                continue;
+            }
+            let x_end = glyph.x + s.ink_width(glyph.c) as isize;
+            match runs.last_mut() {
+                Some((line, _, end)) if *line == glyph.line && prev.map(|p| p + 1) == Some(i) => {
+                    *end = x_end
+                }
+                _ => runs.push((glyph.line, glyph.x, x_end)),
+            }
+            prev = Some(i);
+        }
+
+        let thickness = s.size.div_ceil(2) as isize;
+        let baseline = (s.font.baseline() * s.size) as isize;
+        let mut rects = Vec::new();
+        for (line, x_start, x_end) in runs {
+            // Top of the glyphs of the span
+            let top = self.lines[line].baseline - baseline;
+            let mut decorations = Vec::new();
+            if s.style.underline {
+                decorations.push(baseline);
+            }
+            if s.style.strikethrough {
+                decorations.push(baseline / 2 - thickness / 2);
+            }
+            for dy in decorations {
+                let p = PixelPoint {
+                    x: x_start,
+                    y: top + dy,
+                };
+                rects.push((p, x_end - x_start, thickness));
+            }
+        }
+        rects
+    }
+
+    /// Top-left corner of the glyph
+    pub fn glyph_pos(
+        &self,
+        spans: &[TextSpan<'_>],
+        glyph: &PlacedGlyph,
+        pos: PixelPoint,
+    ) -> PixelPoint {
+        let s = &spans[glyph.span];
+        let ascent = (s.font.baseline() * s.size) as isize;
+        PixelPoint {
+            x: pos.x + glyph.x,
+            y: pos.y + self.lines[glyph.line].baseline - ascent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_font, WHITE};
+
+    use super::*;
+
+    #[test]
+    fn size() {
+        let font = default_font();
+        let spans = [
+            TextSpan::new("ab ", &font, 1, WHITE),
+            TextSpan::new("AB", &font, 2, WHITE),
+        ];
+        // "ab AB"
+        assert_eq!(rich_text_size(&spans, None), (4 + 4 + 2 + 8 + 6, 7 * 2));
+        // "ab" and "AB"
+        assert_eq!(rich_text_size(&spans, Some(16)), (8 + 6, 7 + 7 * 2));
+        // "ab", "A" and "B"
+        assert_eq!(rich_text_size(&spans, Some(10)), (4 + 3, 7 + 7 * 2 + 7 * 2));
+    }
+}
@@ -0,0 +1,402 @@
+use std::str::Chars;
+
+use super::{
+    font::Glyph,
+    glyph_cache::{GlyphCache, GlyphMask},
+    offset_from_middle_iter,
+    rich_text::TextSpan,
+    Canvas, EvenF, Font, Pixel, PixelPoint, PixelPointF, Pixels2D,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStyle {
+    /// Synthetic bold that widens each glyph by half a dot
+    pub bold: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub outline: Option<TextOutline>,
+    pub shadow: Option<TextShadow>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextOutline {
+    /// In pixels
+    pub width: usize,
+    pub color: Pixel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextShadow {
+    /// In pixels
+    pub offset: PixelPoint,
+    pub color: Pixel,
+}
+
+impl<'pixels, P> Canvas<'pixels, P>
+where
+    P: Pixels2D,
+{
+    pub fn pixel_text(
+        &mut self,
+        text: &str,
+        pos: PixelPoint,
+        font: &Font,
+        size: usize,
+        color: Pixel,
+    ) {
+        for (glyph, off) in TextLayout::new(text, font, size) {
+            let x = pos.x + off.x;
+            let y = pos.y + off.y;
+            for p in glyph.points() {
+                for y_i in 0..size {
+                    let y = y + p.y * size as isize + y_i as isize;
+                    if y < 0 || y >= self.height() as isize {
+                        continue;
+                    }
+                    for x_i in 0..size {
+                        let x = x + p.x * size as isize + x_i as isize;
+                        if x < 0 || x >= self.width() as isize {
+                            continue;
+                        }
+                        self.pixel_over_by(x as usize, y as usize, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Same as [`Canvas::pixel_text`] but glyphs are rasterized once into `cache` and blitted from there.
+    pub fn pixel_text_cached(
+        &mut self,
+        text: &str,
+        pos: PixelPoint,
+        cache: &mut GlyphCache<'_>,
+        size: usize,
+        color: Pixel,
+    ) {
+        self.pixel_text_styled(text, pos, cache, size, color, &TextStyle::default());
+    }
+
+    /// Same as [`Canvas::pixel_text_cached`] but decorated by `style`.
+    ///
+    /// Layers are drawn from bottom to top: shadow, outline, glyphs, underline and strikethrough.
+    pub fn pixel_text_styled(
+        &mut self,
+        text: &str,
+        pos: PixelPoint,
+        cache: &mut GlyphCache<'_>,
+        size: usize,
+        color: Pixel,
+        style: &TextStyle,
+    ) {
+        let span = TextSpan {
+            text,
+            font: cache.font(),
+            size,
+            color,
+            style: *style,
+        };
+        self.pixel_spans(&[span], pos, None, cache);
+    }
+
+    /// Blend `color` into the canvas with the alpha of each pixel scaled by the coverage of `mask`.
+    ///
+    /// `pos` is where the top-left corner of the mask goes.
+    pub fn fill_pixel_mask(&mut self, mask: &GlyphMask, pos: PixelPoint, color: Pixel) {
+        let x_min = (-pos.x).max(0) as usize;
+        let y_min = (-pos.y).max(0) as usize;
+        let x_max = (self.width() as isize - pos.x).clamp(0, mask.width() as isize) as usize;
+        let y_max = (self.height() as isize - pos.y).clamp(0, mask.height() as isize) as usize;
+        for mask_y in y_min..y_max {
+            let y = (pos.y + mask_y as isize) as usize;
+            for mask_x in x_min..x_max {
+                let coverage = mask.coverage_at(mask_x, mask_y);
+                if coverage == 0 {
+                    continue;
+                }
+                let x = (pos.x + mask_x as isize) as usize;
+                let color = if coverage == u8::MAX {
+                    color
+                } else {
+                    let alpha = color.a() as usize * coverage as usize / u8::MAX as usize;
+                    Pixel::new(color.r(), color.g(), color.b(), alpha as u8)
+                };
+                self.pixel_over_by(x, y, color);
+            }
+        }
+    }
+
+    /// Same layout as [`Canvas::pixel_text`] but the whole text is rotated by `angle` radians around `pos`.
+    ///
+    /// A positive `angle` rotates clockwise on the screen since the y axis points down.
+    pub fn pixel_text_rotated(
+        &mut self,
+        text: &str,
+        pos: PixelPointF,
+        angle: f64,
+        font: &Font,
+        size: usize,
+        color: Pixel,
+    ) {
+        let (sin, cos) = angle.sin_cos();
+        for (glyph, off) in TextLayout::new(text, font, size) {
+            let x = off.x as f64;
+            let y = off.y as f64;
+            let origin = PixelPointF::new(
+                pos.x().add_f(x * cos - y * sin),
+                pos.y().add_f(x * sin + y * cos),
+            );
+            self.fill_pixel_glyph(glyph, origin, angle, size, color);
+        }
+    }
+
+    /// Lay out a single line of text along the polyline `path`.
+    ///
+    /// - The path is the baseline of the text.
+    /// - `offset` is the distance along the path where the text starts.
+    /// - Each glyph is rotated to the direction of the path under the middle of the glyph.
+    /// - Glyphs not entirely on the path are not drawn, e.g. before its start for a negative `offset`.
+    /// - Curves can be drawn by sampling them into a polyline first.
+    pub fn pixel_text_on_path(
+        &mut self,
+        text: &str,
+        path: &[PixelPointF],
+        offset: f64,
+        font: &Font,
+        size: usize,
+        color: Pixel,
+    ) {
+        let path = PolylinePath::new(path);
+        let size_f = size as f64;
+        let baseline = font.baseline() as f64 * size_f;
+        let length = path.length();
+        let mut s = offset;
+        for c in text.chars() {
+            if c == '\n' {
+                continue;
+            }
+            let glyph = font.glyph_or_unknown(c);
+            let w = glyph.width() as f64 * size_f;
+            let start = s;
+            s += (glyph.width() + 1) as f64 * size_f;
+            if start + w > length {
+                break;
+            }
+            if start < 0. {
+                continue;
+            }
+            let Some((mid, angle)) = path.point_at(start + w / 2.) else {
+                break;
+            };
+
+            // Place the middle of the glyph's baseline on the path
+            let (sin, cos) = angle.sin_cos();
+            let x = -w / 2.;
+            let y = -baseline;
+            let origin = PixelPointF::new(
+                mid.x().add_f(x * cos - y * sin),
+                mid.y().add_f(x * sin + y * cos),
+            );
+            self.fill_pixel_glyph(glyph, origin, angle, size, color);
+        }
+    }
+
+    /// Draw `glyph` scaled by `size` and rotated by `angle` radians around its top-left corner `origin`.
+    ///
+    /// Edges are anti-aliased the same way as [`Canvas::fill_pixel_circle`].
+    fn fill_pixel_glyph(
+        &mut self,
+        glyph: &Glyph,
+        origin: PixelPointF,
+        angle: f64,
+        size: usize,
+        color: Pixel,
+    ) {
+        if glyph.points().is_empty() || size == 0 {
+            return;
+        }
+        let bitmap = glyph.bitmap();
+        let size_f = size as f64;
+        let (sin, cos) = angle.sin_cos();
+
+        // Bounding box of the rotated glyph
+        let w = glyph.width() as f64 * size_f;
+        let h = glyph.height() as f64 * size_f;
+        let corners = [
+            (-0.5, -0.5),
+            (w - 0.5, -0.5),
+            (-0.5, h - 0.5),
+            (w - 0.5, h - 0.5),
+        ];
+        let mut x1 = f64::INFINITY;
+        let mut x2 = f64::NEG_INFINITY;
+        let mut y1 = f64::INFINITY;
+        let mut y2 = f64::NEG_INFINITY;
+        for (u, v) in corners {
+            let x = origin.x().to_f() + u * cos - v * sin;
+            let y = origin.y().to_f() + u * sin + v * cos;
+            x1 = x1.min(x);
+            x2 = x2.max(x);
+            y1 = y1.min(y);
+            y2 = y2.max(y);
+        }
+        if x2 < 0. || y2 < 0. {
+            return;
+        }
+        let x_min = x1.floor().max(0.) as usize;
+        let x_max = x2.ceil().max(0.) as usize;
+        let y_min = y1.floor().max(0.) as usize;
+        let y_max = y2.ceil().max(0.) as usize;
+
+        let is_filled = |u: f64, v: f64| -> bool {
+            let col = ((u + 0.5) / size_f).floor();
+            let row = ((v + 0.5) / size_f).floor();
+            if col < 0. || row < 0. {
+                return false;
+            }
+            let col = col as usize;
+            let row = row as usize;
+            if col >= glyph.width() || row >= glyph.height() {
+                return false;
+            }
+            bitmap[row * glyph.width() + col]
+        };
+
+        for y in y_min..=y_max {
+            if y >= self.height() {
+                break;
+            }
+            for x in x_min..=x_max {
+                if x >= self.width() {
+                    break;
+                }
+                let dx = EvenF::new(x as isize, 0.) - origin.x();
+                let dy = EvenF::new(y as isize, 0.) - origin.y();
+                let dx = dx.to_f();
+                let dy = dy.to_f();
+
+                let mut sub_pixels_filled = 0;
+                offset_from_middle_iter().for_each(|y_off| {
+                    let dy = dy + y_off;
+                    offset_from_middle_iter().for_each(|x_off| {
+                        let dx = dx + x_off;
+                        // Rotate the sample back into the glyph's space
+                        let u = dx * cos + dy * sin;
+                        let v = -dx * sin + dy * cos;
+                        if is_filled(u, v) {
+                            sub_pixels_filled += 1;
+                        }
+                    })
+                });
+                if sub_pixels_filled > 0 {
+                    self.set_anti_aliasing_pixel(x, y, color, sub_pixels_filled);
+                }
+            }
+        }
+    }
+}
+
+struct PolylinePath<'a> {
+    points: &'a [PixelPointF],
+    /// `lengths[i]` is the distance along the path from `points[0]` to `points[i]`
+    lengths: Vec<f64>,
+}
+
+impl<'a> PolylinePath<'a> {
+    pub fn new(points: &'a [PixelPointF]) -> Self {
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut total = 0.;
+        for (i, p) in points.iter().enumerate() {
+            if i > 0 {
+                let (dx, dy) = points[i - 1].f_to(*p);
+                total += (dx * dx + dy * dy).sqrt();
+            }
+            lengths.push(total);
+        }
+        Self { points, lengths }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.lengths.last().copied().unwrap_or(0.)
+    }
+
+    /// Return the point at distance `s` along the path and the direction of the path there
+    pub fn point_at(&self, s: f64) -> Option<(PixelPointF, f64)> {
+        if s < 0. {
+            return None;
+        }
+        for i in 1..self.points.len() {
+            let (start, end) = (self.lengths[i - 1], self.lengths[i]);
+            if s > end || start == end {
+                continue;
+            }
+            let (dx, dy) = self.points[i - 1].f_to(self.points[i]);
+            let t = (s - start) / (end - start);
+            let p = self.points[i - 1];
+            let p = PixelPointF::new(p.x().add_f(dx * t), p.y().add_f(dy * t));
+            return Some((p, dy.atan2(dx)));
+        }
+        None
+    }
+}
+
+/// Top-left corners of the glyphs relative to the start of the text
+pub(crate) struct TextLayout<'a> {
+    chars: Chars<'a>,
+    font: &'a Font,
+    size: usize,
+    x: isize,
+    y: isize,
+    max_height: usize,
+}
+
+impl<'a> TextLayout<'a> {
+    pub fn new(text: &'a str, font: &'a Font, size: usize) -> Self {
+        assert!(size <= isize::MAX as usize, "size is too big");
+        Self {
+            chars: text.chars(),
+            font,
+            size,
+            x: 0,
+            y: 0,
+            max_height: 0,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<(char, PixelPoint)> {
+        loop {
+            let c = self.chars.next()?;
+            if c == '\n' {
+                self.x = 0;
+                let dy = (self.max_height + 1) * self.size;
+                assert!(dy <= isize::MAX as usize, "dy is too big");
+                self.y += dy as isize;
+                self.max_height = 0;
+                continue;
+            }
+
+            let glyph = self.font.glyph_or_unknown(c);
+            if !glyph.points().is_empty() {
+                self.max_height = self.max_height.max(glyph.height() + 1);
+            }
+            let pos = PixelPoint {
+                x: self.x,
+                y: self.y,
+            };
+            let dx = (glyph.width() + 1) * self.size;
+            assert!(dx <= isize::MAX as usize, "dx is too big");
+            self.x += dx as isize;
+            return Some((c, pos));
+        }
+    }
+}
+
+impl<'a> Iterator for TextLayout<'a> {
+    type Item = (&'a Glyph, PixelPoint);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let font = self.font;
+        self.next_char()
+            .map(|(c, pos)| (font.glyph_or_unknown(c), pos))
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{HeapPixels2D, Pixel, Pixels2D};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffError {
+    /// Two images to compare have different widths or heights
+    DifferentSizes {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+impl std::fmt::Display for DiffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffError::DifferentSizes { expected, actual } => write!(
+                f,
+                "expected size {}x{} but got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiffError {}
+
+/// How different two images may be while still matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tolerance {
+    /// Channel differences up to this are ignored
+    pub threshold: u8,
+    /// How many pixels may differ by more than `threshold`
+    pub max_pixels: usize,
+}
+
+/// Per-pixel difference metrics over all four channels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageDiff {
+    pub tolerance: Tolerance,
+    /// The largest difference of any channel
+    pub max_delta: u8,
+    /// Root-mean-square error of the channels, from 0 to 255
+    pub rmse: f64,
+    /// Peak signal-to-noise ratio in dB, infinite for identical images
+    ///
+    /// - Ref: <https://en.wikipedia.org/wiki/Peak_signal-to-noise_ratio>
+    pub psnr: f64,
+    /// Pixels with any channel differing by more than the threshold
+    pub pixels_above_threshold: usize,
+}
+
+impl ImageDiff {
+    pub fn new<E, A>(expected: &E, actual: &A, tolerance: Tolerance) -> Result<Self, DiffError>
+    where
+        E: Pixels2D,
+        A: Pixels2D,
+    {
+        check_same_size(expected, actual)?;
+
+        let mut max_delta = 0;
+        let mut squared_sum = 0.;
+        let mut pixels_above_threshold = 0;
+        for (e, a) in expected.pixels().iter().zip(actual.pixels()) {
+            let delta = max_channel_delta(*e, *a);
+            max_delta = max_delta.max(delta);
+            if delta > tolerance.threshold {
+                pixels_above_threshold += 1;
+            }
+            squared_sum += channels(*e)
+                .into_iter()
+                .zip(channels(*a))
+                .map(|(e, a)| (e as f64 - a as f64).powi(2))
+                .sum::<f64>();
+        }
+
+        let samples = expected.pixels().len() * 4;
+        let mse = if samples == 0 {
+            0.
+        } else {
+            squared_sum / samples as f64
+        };
+        let psnr = if mse == 0. {
+            f64::INFINITY
+        } else {
+            10. * ((u8::MAX as f64).powi(2) / mse).log10()
+        };
+        Ok(Self {
+            tolerance,
+            max_delta,
+            rmse: mse.sqrt(),
+            psnr,
+            pixels_above_threshold,
+        })
+    }
+
+    /// Whether few enough pixels differ by more than the threshold
+    pub fn is_within_tolerance(&self) -> bool {
+        self.pixels_above_threshold <= self.tolerance.max_pixels
+    }
+}
+
+/// Shortcut for [`ImageDiff::is_within_tolerance`]
+pub fn images_match<E, A>(expected: &E, actual: &A, tolerance: Tolerance) -> bool
+where
+    E: Pixels2D,
+    A: Pixels2D,
+{
+    ImageDiff::new(expected, actual, tolerance).is_ok_and(|diff| diff.is_within_tolerance())
+}
+
+/// A dimmed grayscale copy of `expected` with differences highlighted:
+///
+/// - Red: any channel differs by more than `threshold`
+/// - Yellow: the pixels differ by at most `threshold`
+pub fn diff_image<E, A>(expected: &E, actual: &A, threshold: u8) -> Result<HeapPixels2D, DiffError>
+where
+    E: Pixels2D,
+    A: Pixels2D,
+{
+    const ABOVE: Pixel = Pixel::new(u8::MAX, 0, 0, u8::MAX);
+    const BELOW: Pixel = Pixel::new(u8::MAX, u8::MAX, 0, u8::MAX);
+
+    check_same_size(expected, actual)?;
+    let pixels = expected
+        .pixels()
+        .iter()
+        .zip(actual.pixels())
+        .map(|(e, a)| match max_channel_delta(*e, *a) {
+            0 => {
+                // Rec. 601 luma premultiplied by alpha, at a third of the brightness
+                let luma = (e.r() as u32 * 299 + e.g() as u32 * 587 + e.b() as u32 * 114) / 1000;
+                let v = (luma * e.a() as u32 / u8::MAX as u32 / 3) as u8;
+                Pixel::new(v, v, v, u8::MAX)
+            }
+            delta if delta > threshold => ABOVE,
+            _ => BELOW,
+        })
+        .collect();
+    Ok(HeapPixels2D::from_pixels(
+        expected.width(),
+        expected.height(),
+        pixels,
+    ))
+}
+
+fn check_same_size<E, A>(expected: &E, actual: &A) -> Result<(), DiffError>
+where
+    E: Pixels2D,
+    A: Pixels2D,
+{
+    let expected = (expected.width(), expected.height());
+    let actual = (actual.width(), actual.height());
+    if expected != actual {
+        return Err(DiffError::DifferentSizes { expected, actual });
+    }
+    Ok(())
+}
+
+fn channels(p: Pixel) -> [u8; 4] {
+    [p.r(), p.g(), p.b(), p.a()]
+}
+
+fn max_channel_delta(a: Pixel, b: Pixel) -> u8 {
+    channels(a)
+        .into_iter()
+        .zip(channels(b))
+        .map(|(a, b)| a.abs_diff(b))
+        .max()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics() {
+        let black = Pixel::new(0, 0, 0, u8::MAX);
+        let expected = HeapPixels2D::from_pixels(2, 1, vec![black, black]);
+        let actual = HeapPixels2D::from_pixels(2, 1, vec![black, Pixel::new(2, 0, 0, u8::MAX)]);
+
+        let same = ImageDiff::new(&expected, &expected, Tolerance::default()).unwrap();
+        assert_eq!(same.max_delta, 0);
+        assert_eq!(same.rmse, 0.);
+        assert_eq!(same.psnr, f64::INFINITY);
+        assert!(same.is_within_tolerance());
+
+        let diff = ImageDiff::new(&expected, &actual, Tolerance::default()).unwrap();
+        assert_eq!(diff.max_delta, 2);
+        // One of eight channels is off by 2
+        assert_eq!(diff.rmse, (4. / 8f64).sqrt());
+        assert!((diff.psnr - 51.14).abs() < 0.01);
+        assert_eq!(diff.pixels_above_threshold, 1);
+        assert!(!diff.is_within_tolerance());
+
+        let loose = Tolerance {
+            threshold: 2,
+            max_pixels: 0,
+        };
+        assert!(images_match(&expected, &actual, loose));
+        let image = diff_image(&expected, &actual, 1).unwrap();
+        assert_eq!(image.pixels(), [black, Pixel::new(u8::MAX, 0, 0, u8::MAX)]);
+    }
+
+    #[test]
+    fn different_sizes() {
+        let a = HeapPixels2D::new(2, 1, Pixel::new(0, 0, 0, 0));
+        let b = HeapPixels2D::new(1, 2, Pixel::new(0, 0, 0, 0));
+        assert_eq!(
+            ImageDiff::new(&a, &b, Tolerance::default()),
+            Err(DiffError::DifferentSizes {
+                expected: (2, 1),
+                actual: (1, 2)
+            })
+        );
+        assert!(!images_match(&a, &b, Tolerance::default()));
+    }
+}
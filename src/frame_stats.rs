@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+
+use crate::{rich_text_size, Canvas, Font, Pixel, PixelPoint, Pixels2D, TextSpan};
+
+/// Rolling frame timing statistics over the last frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameStats {
+    capacity: usize,
+    target_frame_ms: f64,
+    /// Time between frames, oldest first
+    frame_times_ms: VecDeque<f64>,
+    /// Time spent in [`crate::Render::render`], oldest first
+    render_times_ms: VecDeque<f64>,
+    frames: usize,
+    dropped_frames: usize,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(120, 1000. / 60.)
+    }
+}
+
+impl FrameStats {
+    /// Keep the last `capacity` frames; frames later than `target_frame_ms` count as dropped
+    pub fn new(capacity: usize, target_frame_ms: f64) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            target_frame_ms,
+            frame_times_ms: VecDeque::with_capacity(capacity),
+            render_times_ms: VecDeque::with_capacity(capacity),
+            frames: 0,
+            dropped_frames: 0,
+        }
+    }
+
+    /// Record a frame which came `frame_ms` after the previous one and took `render_ms` to render
+    pub fn record(&mut self, frame_ms: f64, render_ms: f64) {
+        if self.frame_times_ms.len() == self.capacity {
+            self.frame_times_ms.pop_front();
+            self.render_times_ms.pop_front();
+        }
+        self.frame_times_ms.push_back(frame_ms);
+        self.render_times_ms.push_back(render_ms);
+        self.frames += 1;
+        // Missed refreshes between this frame and the previous one
+        let missed = (frame_ms / self.target_frame_ms).round() - 1.;
+        if missed >= 1. {
+            self.dropped_frames += missed as usize;
+        }
+    }
+
+    pub fn target_frame_ms(&self) -> f64 {
+        self.target_frame_ms
+    }
+
+    /// All frames ever recorded
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// All refreshes missed since the first frame
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped_frames
+    }
+
+    /// Frame times of the last frames, oldest first
+    pub fn frame_times_ms(&self) -> impl Iterator<Item = f64> + '_ {
+        self.frame_times_ms.iter().copied()
+    }
+
+    pub fn last_frame_ms(&self) -> Option<f64> {
+        self.frame_times_ms.back().copied()
+    }
+
+    pub fn average_frame_ms(&self) -> Option<f64> {
+        average(&self.frame_times_ms)
+    }
+
+    pub fn average_render_ms(&self) -> Option<f64> {
+        average(&self.render_times_ms)
+    }
+
+    /// Frames per second from the average frame time
+    pub fn fps(&self) -> Option<f64> {
+        self.average_frame_ms()
+            .filter(|ms| *ms > 0.)
+            .map(|ms| 1000. / ms)
+    }
+
+    /// The frame time which `percentile` percent of the last frames are at or below, e.g. 95 or 99
+    ///
+    /// - Ref: <https://en.wikipedia.org/wiki/Percentile#The_nearest-rank_method>
+    pub fn percentile_frame_ms(&self, percentile: f64) -> Option<f64> {
+        if self.frame_times_ms.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.frame_times_ms.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (percentile.clamp(0., 100.) / 100. * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.max(1) - 1])
+    }
+}
+
+fn average(values: &VecDeque<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsOverlay {
+    pub corner: Corner,
+    pub font_size: usize,
+    /// Size of the frame-time graph; one bar per frame, newest on the right
+    pub graph_width: usize,
+    pub graph_height: usize,
+    pub background: Pixel,
+    pub text_color: Pixel,
+    /// Bars of frames within the target frame time
+    pub bar_color: Pixel,
+    /// Bars of frames over the target frame time
+    pub slow_bar_color: Pixel,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            corner: Corner::TopLeft,
+            font_size: 2,
+            graph_width: 120,
+            graph_height: 32,
+            background: Pixel::new(0, 0, 0, 0xc0),
+            text_color: Pixel::new(0xff, 0xff, 0xff, 0xff),
+            bar_color: Pixel::new(0x40, 0xc0, 0x40, 0xff),
+            slow_bar_color: Pixel::new(0xe0, 0x40, 0x40, 0xff),
+        }
+    }
+}
+
+/// Draw the FPS, frame times and a frame-time graph in a corner of `canvas`.
+///
+/// The graph spans twice the target frame time, with a line at the target.
+pub fn draw_frame_stats<CP>(
+    canvas: &mut Canvas<'_, CP>,
+    stats: &FrameStats,
+    font: &Font,
+    overlay: &StatsOverlay,
+) where
+    CP: Pixels2D,
+{
+    const PADDING: isize = 4;
+
+    let fmt = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{v:.1}"));
+    let lines = [
+        format!("{} FPS", fmt(stats.fps())),
+        format!(
+            "{} ms p95 {}",
+            fmt(stats.average_frame_ms()),
+            fmt(stats.percentile_frame_ms(95.))
+        ),
+        format!(
+            "render {} ms dropped {}",
+            fmt(stats.average_render_ms()),
+            stats.dropped_frames()
+        ),
+    ];
+    let sizes = lines.each_ref().map(|line| {
+        let span = TextSpan::new(line, font, overlay.font_size, overlay.text_color);
+        rich_text_size(&[span], None)
+    });
+    let text_width = sizes.iter().map(|(w, _)| *w).max().unwrap_or(0) as isize;
+    let line_height =
+        (sizes.iter().map(|(_, h)| *h).max().unwrap_or(0) + overlay.font_size) as isize;
+    let text_height = line_height * lines.len() as isize;
+    let graph_width = overlay.graph_width as isize;
+    let graph_height = overlay.graph_height as isize;
+    let panel_width = graph_width.max(text_width) + 2 * PADDING;
+    let panel_height = text_height + graph_height + 3 * PADDING;
+
+    let (width, height) = (canvas.width() as isize, canvas.height() as isize);
+    let x = match overlay.corner {
+        Corner::TopLeft | Corner::BottomLeft => 0,
+        Corner::TopRight | Corner::BottomRight => width - panel_width,
+    };
+    let y = match overlay.corner {
+        Corner::TopLeft | Corner::TopRight => 0,
+        Corner::BottomLeft | Corner::BottomRight => height - panel_height,
+    };
+    canvas.fill_pixel_rect(
+        PixelPoint { x, y },
+        panel_width,
+        panel_height,
+        overlay.background,
+    );
+    for (i, line) in lines.iter().enumerate() {
+        let pos = PixelPoint {
+            x: x + PADDING,
+            y: y + PADDING + line_height * i as isize,
+        };
+        canvas.pixel_text(line, pos, font, overlay.font_size, overlay.text_color);
+    }
+
+    let graph_left = x + PADDING;
+    let graph_bottom = y + panel_height - PADDING - 1;
+    let max_ms = 2. * stats.target_frame_ms();
+    let frame_times: Vec<f64> = stats.frame_times_ms().collect();
+    let shown = &frame_times[frame_times.len().saturating_sub(overlay.graph_width)..];
+    let offset = graph_width - shown.len() as isize;
+    for (i, ms) in shown.iter().enumerate() {
+        let bar_height = ((ms / max_ms).min(1.) * graph_height as f64).round() as isize;
+        let color = if *ms > stats.target_frame_ms() {
+            overlay.slow_bar_color
+        } else {
+            overlay.bar_color
+        };
+        let p = PixelPoint {
+            x: graph_left + offset + i as isize,
+            y: graph_bottom,
+        };
+        canvas.fill_pixel_rect(p, 1, -bar_height, color);
+    }
+    // The target frame time
+    let target = PixelPoint {
+        x: graph_left,
+        y: graph_bottom - graph_height / 2,
+    };
+    canvas.fill_pixel_rect(target, graph_width, 1, overlay.text_color);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{default_font, HeapPixels2D};
+
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut stats = FrameStats::new(4, 10.);
+        assert_eq!(stats.fps(), None);
+        for ms in [10., 10., 30., 10., 20.] {
+            stats.record(ms, 1.);
+        }
+        assert_eq!(stats.frames(), 5);
+        // 30 ms misses 2 refreshes and 20 ms misses 1
+        assert_eq!(stats.dropped_frames(), 3);
+        assert_eq!(
+            stats.frame_times_ms().collect::<Vec<_>>(),
+            [10., 30., 10., 20.]
+        );
+        assert_eq!(stats.last_frame_ms(), Some(20.));
+        assert_eq!(stats.average_frame_ms(), Some(17.5));
+        assert_eq!(stats.fps(), Some(1000. / 17.5));
+        assert_eq!(stats.average_render_ms(), Some(1.));
+        assert_eq!(stats.percentile_frame_ms(50.), Some(10.));
+        assert_eq!(stats.percentile_frame_ms(95.), Some(30.));
+    }
+
+    #[test]
+    fn overlay_corner() {
+        let background = Pixel::new(0, 0, 0, 0xff);
+        let mut pixels = HeapPixels2D::new(300, 200, background);
+        let mut stats = FrameStats::default();
+        stats.record(16., 2.);
+        let overlay = StatsOverlay {
+            corner: Corner::BottomRight,
+            background: Pixel::new(0x10, 0x10, 0x10, 0xff),
+            ..Default::default()
+        };
+        draw_frame_stats(
+            &mut Canvas::new_entire(&mut pixels),
+            &stats,
+            &default_font(),
+            &overlay,
+        );
+        assert_eq!(pixels.pixels()[0], background);
+        assert_eq!(pixels.pixels()[299 + 199 * 300], overlay.background);
+        // The bar of the only frame, 16 / 33.3 of the graph height
+        let bar: Vec<_> = (0..pixels.pixels().len())
+            .filter(|i| pixels.pixels()[*i] == overlay.bar_color)
+            .map(|i| (i % 300, i / 300))
+            .collect();
+        assert_eq!(bar.len(), 15);
+        assert!(bar.iter().all(|(x, _)| *x == bar[0].0));
+        assert_eq!(bar.last().unwrap().1, 195);
+    }
+}
@@ -1,6 +1,14 @@
 mod canvas;
+mod diff;
+mod frame_stats;
 mod math;
 mod render;
+mod scene;
+mod tween;
 
 pub use canvas::*;
+pub use diff::*;
+pub use frame_stats::*;
 pub use render::*;
+pub use scene::*;
+pub use tween::*;
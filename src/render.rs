@@ -1,6 +1,89 @@
-use crate::Pixel;
+use crate::{DirtyRect, Pixel};
 
 pub trait Render {
+    /// Advance the state by `dt_ms` without drawing; does nothing by default
+    ///
+    /// Runners call it before each [`Render::render`], or once per step with a fixed timestep.
+    /// Renders which implement it should only draw in [`Render::render`].
+    fn update(&mut self, _dt_ms: f64) {}
+
+    /// Draw the current state; `dt_ms` is the total time passed to [`Render::update`] since the last call
     fn render(&mut self, dt_ms: f64);
     fn pixels(&self) -> &[Pixel];
+
+    /// Called between frames for each input event; ignores all events by default
+    fn event(&mut self, _event: &Event) {}
+
+    /// The region of [`Render::pixels`] changed by the last [`Render::render`], e.g. from [`crate::Canvas::dirty_rect`]
+    ///
+    /// Runners may then only upload that region. `None`, the default, means everything may have changed.
+    fn dirty_rect(&self) -> Option<DirtyRect> {
+        None
+    }
+}
+
+/// Input delivered to [`Render::event`]
+///
+/// Pointer coordinates are in pixels relative to the top-left corner of the rendered image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PointerMove {
+        x: f64,
+        y: f64,
+    },
+    PointerDown {
+        x: f64,
+        y: f64,
+        button: PointerButton,
+    },
+    PointerUp {
+        x: f64,
+        y: f64,
+        button: PointerButton,
+    },
+    /// Scroll distances in pixels
+    Wheel {
+        dx: f64,
+        dy: f64,
+    },
+    KeyDown(KeyEvent),
+    KeyUp(KeyEvent),
+    /// The size of the rendered image in pixels
+    Resize {
+        width: usize,
+        height: usize,
+    },
+    /// Whether the render target gained or lost the keyboard focus
+    Focus(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerButton {
+    Primary,
+    Secondary,
+    Middle,
+    Other(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The produced character or a name like `Enter` or `ArrowLeft`
+    ///
+    /// - Ref: <https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_key_values>
+    pub key: String,
+    /// The physical key like `KeyA` regardless of the layout
+    ///
+    /// - Ref: <https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_code_values>
+    pub code: String,
+    /// Auto-repeated by holding the key down
+    pub repeat: bool,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
 }
@@ -0,0 +1,704 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{
+    canvas::{offset_from_middle_iter, RESOLUTION},
+    rich_text_size, Canvas, Font, HeapPixels2D, Pixel, PixelPoint, Pixels2D, TextSpan,
+};
+
+/// 2D affine transform mapping `(x, y)` to `(a x + c y + e, b x + d y + f)`
+///
+/// - Ref: <https://developer.mozilla.org/en-US/docs/Web/SVG/Attribute/transform#matrix>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        a: 1.,
+        b: 0.,
+        c: 0.,
+        d: 1.,
+        e: 0.,
+        f: 0.,
+    };
+
+    pub fn translate(x: f64, y: f64) -> Self {
+        Self {
+            e: x,
+            f: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Clockwise on screen since y points down
+    pub fn rotate(radians: f64) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(x: f64, y: f64) -> Self {
+        Self {
+            a: x,
+            d: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// `self` followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// `None` if the transform collapses the plane
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+        Some(Transform {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+}
+
+/// What a node draws in its local coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content<'font> {
+    /// From the origin to `(width, height)`
+    Rect {
+        width: f64,
+        height: f64,
+        color: Pixel,
+    },
+    /// Centered at the origin
+    Circle { radius: f64, color: Pixel },
+    Triangle {
+        vertices: [(f64, f64); 3],
+        color: Pixel,
+    },
+    /// [`Canvas::pixel_text`] with its top-left corner at the origin
+    Text {
+        text: String,
+        font: &'font Font,
+        size: usize,
+        color: Pixel,
+    },
+    /// One unit per pixel with its top-left corner at the origin
+    Image(HeapPixels2D),
+}
+
+impl Content<'_> {
+    /// Local bounds as `(x_min, y_min, x_max, y_max)`
+    fn bounds(&self) -> (f64, f64, f64, f64) {
+        match self {
+            Content::Rect { width, height, .. } => {
+                (width.min(0.), height.min(0.), width.max(0.), height.max(0.))
+            }
+            Content::Circle { radius, .. } => (-radius, -radius, *radius, *radius),
+            Content::Triangle { vertices, .. } => vertices.iter().fold(
+                (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+                |(x0, y0, x1, y1), (x, y)| (x0.min(*x), y0.min(*y), x1.max(*x), y1.max(*y)),
+            ),
+            Content::Text {
+                text, font, size, ..
+            } => {
+                let span = TextSpan::new(text, font, *size, Pixel::new(0, 0, 0, 0));
+                let (width, height) = rich_text_size(&[span], None);
+                (0., 0., width as f64, height as f64)
+            }
+            Content::Image(image) => (0., 0., image.width() as f64, image.height() as f64),
+        }
+    }
+
+    /// Whether the local point is inside the shape, or inside the bounds of text and images
+    fn contains(&self, x: f64, y: f64) -> bool {
+        match self {
+            Content::Rect { .. } | Content::Text { .. } | Content::Image(_) => {
+                let (x0, y0, x1, y1) = self.bounds();
+                (x0..x1).contains(&x) && (y0..y1).contains(&y)
+            }
+            Content::Circle { radius, .. } => x * x + y * y <= radius * radius,
+            Content::Triangle { vertices, .. } => {
+                let [p1, p2, p3] = *vertices;
+                let side = |(ax, ay): (f64, f64), (bx, by): (f64, f64)| {
+                    (bx - ax) * (y - ay) - (by - ay) * (x - ax)
+                };
+                let (s1, s2, s3) = (side(p1, p2), side(p2, p3), side(p3, p1));
+                (s1 >= 0. && s2 >= 0. && s3 >= 0.) || (s1 <= 0. && s2 <= 0. && s3 <= 0.)
+            }
+        }
+    }
+}
+
+/// A node of a [`Scene`]; nodes without content group their children
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'font> {
+    /// From the node to its parent
+    pub transform: Transform,
+    /// Siblings with a higher `z_index` are drawn on top, ties in insertion order
+    pub z_index: i32,
+    /// Hidden nodes and their descendants are neither drawn nor hit
+    pub visible: bool,
+    /// Multiplies the alpha of the node and its descendants, which are still composited one by one
+    pub opacity: f64,
+    pub content: Option<Content<'font>>,
+}
+
+impl Default for Node<'_> {
+    fn default() -> Self {
+        Self {
+            transform: Transform::IDENTITY,
+            z_index: 0,
+            visible: true,
+            opacity: 1.,
+            content: None,
+        }
+    }
+}
+
+impl<'font> Node<'font> {
+    pub fn new(content: Content<'font>) -> Self {
+        Self {
+            content: Some(content),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry<'font> {
+    node: Node<'font>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// A retained tree of nodes drawn into a [`Canvas`].
+///
+/// Scene coordinates are canvas pixels, where pixel `(x, y)` is the unit square from `(x, y)` to `(x + 1, y + 1)`.
+/// Each pixel is covered by the share of its sub-pixel samples inside the shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene<'font> {
+    entries: Vec<Option<Entry<'font>>>,
+    text_images: TextImages<'font>,
+}
+
+/// Rasterized [`Content::Text`] of each node, redrawn only when the text changes
+#[derive(Debug, Clone, Default)]
+struct TextImages<'font>(RefCell<HashMap<NodeId, TextImage<'font>>>);
+
+/// Only a cache, so it doesn't make scenes different
+impl PartialEq for TextImages<'_> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TextImage<'font> {
+    text: String,
+    font: &'font Font,
+    size: usize,
+    color: Pixel,
+    image: HeapPixels2D,
+}
+
+impl Default for Scene<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'font> Scene<'font> {
+    /// A scene with an empty root group
+    pub fn new() -> Self {
+        Self {
+            entries: vec![Some(Entry {
+                node: Node::default(),
+                parent: None,
+                children: Vec::new(),
+            })],
+            text_images: TextImages::default(),
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Panics if `parent` is not in the scene
+    pub fn add(&mut self, parent: NodeId, node: Node<'font>) -> NodeId {
+        let id = NodeId(self.entries.len());
+        self.entry_mut(parent)
+            .expect("the parent is not in the scene")
+            .children
+            .push(id);
+        self.entries.push(Some(Entry {
+            node,
+            parent: Some(parent),
+            children: Vec::new(),
+        }));
+        id
+    }
+
+    /// Remove the node and its descendants and return the node; the root cannot be removed
+    pub fn remove(&mut self, id: NodeId) -> Option<Node<'font>> {
+        let parent = self.entry(id)?.parent?;
+        if let Some(parent) = self.entry_mut(parent) {
+            parent.children.retain(|c| *c != id);
+        }
+        let mut stack = vec![id];
+        let mut removed = None;
+        while let Some(id) = stack.pop() {
+            if let Some(entry) = self.entries[id.0].take() {
+                self.text_images.0.borrow_mut().remove(&id);
+                stack.extend(entry.children);
+                removed.get_or_insert(entry.node);
+            }
+        }
+        removed
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node<'font>> {
+        self.entry(id).map(|e| &e.node)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node<'font>> {
+        self.entry_mut(id).map(|e| &mut e.node)
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id)?.parent
+    }
+
+    /// In insertion order
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.entry(id).map_or(&[], |e| &e.children)
+    }
+
+    /// From the node to the scene
+    pub fn world_transform(&self, id: NodeId) -> Option<Transform> {
+        let mut transform = self.node(id)?.transform;
+        let mut parent = self.parent(id);
+        while let Some(p) = parent {
+            transform = transform.then(&self.node(p)?.transform);
+            parent = self.parent(p);
+        }
+        Some(transform)
+    }
+
+    /// Draw the visible nodes depth first, each node before its children
+    pub fn render<CP>(&self, canvas: &mut Canvas<'_, CP>)
+    where
+        CP: Pixels2D,
+    {
+        self.visit(
+            self.root(),
+            &Transform::IDENTITY,
+            1.,
+            &mut |id, node, transform, opacity| {
+                if let Some(content) = &node.content {
+                    draw_content(canvas, content, transform, opacity, id, &self.text_images);
+                }
+            },
+        );
+    }
+
+    /// The topmost visible node with content at the scene point
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<NodeId> {
+        let mut hit = None;
+        // The last node drawn over the point is the topmost
+        self.visit(
+            self.root(),
+            &Transform::IDENTITY,
+            1.,
+            &mut |id, node, transform, _| {
+                if let (Some(content), Some(inverse)) = (&node.content, transform.inverse()) {
+                    let (lx, ly) = inverse.apply(x, y);
+                    if content.contains(lx, ly) {
+                        hit = Some(id);
+                    }
+                }
+            },
+        );
+        hit
+    }
+
+    fn entry(&self, id: NodeId) -> Option<&Entry<'font>> {
+        self.entries.get(id.0)?.as_ref()
+    }
+
+    fn entry_mut(&mut self, id: NodeId) -> Option<&mut Entry<'font>> {
+        self.entries.get_mut(id.0)?.as_mut()
+    }
+
+    /// Children in drawing order
+    fn sorted_children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = self.children(id).to_vec();
+        children.sort_by_key(|c| self.node(*c).map_or(0, |n| n.z_index));
+        children
+    }
+
+    /// Call `f` with the world transform and opacity of each visible node in drawing order
+    fn visit<F>(&self, id: NodeId, parent: &Transform, parent_opacity: f64, f: &mut F)
+    where
+        F: FnMut(NodeId, &Node<'font>, &Transform, f64),
+    {
+        let Some(node) = self.node(id) else {
+            return;
+        };
+        if !node.visible {
+            return;
+        }
+        let transform = node.transform.then(parent);
+        let opacity = parent_opacity * node.opacity.clamp(0., 1.);
+        f(id, node, &transform, opacity);
+        for c in self.sorted_children(id) {
+            self.visit(c, &transform, opacity, f);
+        }
+    }
+}
+
+fn draw_content<'font, CP>(
+    canvas: &mut Canvas<'_, CP>,
+    content: &Content<'font>,
+    transform: &Transform,
+    opacity: f64,
+    id: NodeId,
+    text_images: &TextImages<'font>,
+) where
+    CP: Pixels2D,
+{
+    let Some(inverse) = transform.inverse() else {
+        return;
+    };
+    match content {
+        Content::Rect { color, .. }
+        | Content::Circle { color, .. }
+        | Content::Triangle { color, .. } => {
+            let color = with_opacity(*color, opacity);
+            fill_mapped(canvas, content.bounds(), transform, &inverse, |x, y| {
+                content.contains(x, y).then_some(color)
+            });
+        }
+        Content::Text {
+            text,
+            font,
+            size,
+            color,
+        } => {
+            let mut images = text_images.0.borrow_mut();
+            let (_, _, width, height) = content.bounds();
+            let (width, height) = (width as usize, height as usize);
+            // Nothing to draw, e.g. for empty text
+            if width == 0 || height == 0 {
+                images.remove(&id);
+                return;
+            }
+            let cached = images.get(&id).is_some_and(|cached| {
+                cached.text == *text
+                    && std::ptr::eq(cached.font, *font)
+                    && cached.size == *size
+                    && cached.color == *color
+            });
+            if !cached {
+                let mut image = HeapPixels2D::new(width, height, Pixel::new(0, 0, 0, 0));
+                Canvas::new_entire(&mut image).pixel_text(
+                    text,
+                    PixelPoint { x: 0, y: 0 },
+                    font,
+                    *size,
+                    *color,
+                );
+                let text_image = TextImage {
+                    text: text.clone(),
+                    font,
+                    size: *size,
+                    color: *color,
+                    image,
+                };
+                images.insert(id, text_image);
+            }
+            draw_image(canvas, &images[&id].image, transform, &inverse, opacity);
+        }
+        Content::Image(image) => draw_image(canvas, image, transform, &inverse, opacity),
+    }
+}
+
+fn draw_image<CP>(
+    canvas: &mut Canvas<'_, CP>,
+    image: &HeapPixels2D,
+    transform: &Transform,
+    inverse: &Transform,
+    opacity: f64,
+) where
+    CP: Pixels2D,
+{
+    let (width, height) = (image.width() as f64, image.height() as f64);
+    fill_mapped(
+        canvas,
+        (0., 0., width, height),
+        transform,
+        inverse,
+        |x, y| {
+            if !(0. ..width).contains(&x) || !(0. ..height).contains(&y) {
+                return None;
+            }
+            // Nearest neighbor
+            let p = image.pixels()[y as usize * image.width() + x as usize];
+            Some(with_opacity(p, opacity))
+        },
+    );
+}
+
+/// Composite the average of `sample` at the sub-pixels of each pixel over the transformed `bounds`
+fn fill_mapped<CP, F>(
+    canvas: &mut Canvas<'_, CP>,
+    (x0, y0, x1, y1): (f64, f64, f64, f64),
+    transform: &Transform,
+    inverse: &Transform,
+    sample: F,
+) where
+    CP: Pixels2D,
+    F: Fn(f64, f64) -> Option<Pixel>,
+{
+    let corners = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| transform.apply(x, y));
+    let x_min = corners.iter().map(|c| c.0).fold(f64::MAX, f64::min);
+    let x_max = corners.iter().map(|c| c.0).fold(f64::MIN, f64::max);
+    let y_min = corners.iter().map(|c| c.1).fold(f64::MAX, f64::min);
+    let y_max = corners.iter().map(|c| c.1).fold(f64::MIN, f64::max);
+    let x_range =
+        x_min.floor().max(0.) as usize..(x_max.ceil().max(0.) as usize).min(canvas.width());
+    let y_range =
+        y_min.floor().max(0.) as usize..(y_max.ceil().max(0.) as usize).min(canvas.height());
+    let samples = (RESOLUTION * RESOLUTION) as u32;
+    for y in y_range {
+        for x in x_range.clone() {
+            // Sums of the alpha and the colors premultiplied by it
+            let (mut r, mut g, mut b, mut a) = (0, 0, 0, 0);
+            for dy in offset_from_middle_iter() {
+                for dx in offset_from_middle_iter() {
+                    let (lx, ly) = inverse.apply(x as f64 + 0.5 + dx, y as f64 + 0.5 + dy);
+                    if let Some(color) = sample(lx, ly) {
+                        let alpha = color.a() as u32;
+                        r += color.r() as u32 * alpha;
+                        g += color.g() as u32 * alpha;
+                        b += color.b() as u32 * alpha;
+                        a += alpha;
+                    }
+                }
+            }
+            if a == 0 {
+                continue;
+            }
+            let color = Pixel::new(
+                (r / a) as u8,
+                (g / a) as u8,
+                (b / a) as u8,
+                (a / samples) as u8,
+            );
+            canvas.pixel_over_by(x, y, color);
+        }
+    }
+}
+
+fn with_opacity(color: Pixel, opacity: f64) -> Pixel {
+    let a = (color.a() as f64 * opacity).round() as u8;
+    Pixel::new(color.r(), color.g(), color.b(), a)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const BLACK: Pixel = Pixel::new(0, 0, 0, 0xff);
+    const RED: Pixel = Pixel::new(0xff, 0, 0, 0xff);
+    const BLUE: Pixel = Pixel::new(0, 0, 0xff, 0xff);
+
+    fn rect(width: f64, height: f64, color: Pixel) -> Content<'static> {
+        Content::Rect {
+            width,
+            height,
+            color,
+        }
+    }
+
+    #[test]
+    fn transforms() {
+        let t = Transform::scale(2., 2.)
+            .then(&Transform::rotate(FRAC_PI_2))
+            .then(&Transform::translate(10., 0.));
+        let (x, y) = t.apply(1., 0.);
+        assert!((x - 10.).abs() < 1e-9 && (y - 2.).abs() < 1e-9);
+        let (x, y) = t.inverse().unwrap().apply(x, y);
+        assert!((x - 1.).abs() < 1e-9 && y.abs() < 1e-9);
+        assert_eq!(Transform::scale(0., 1.).inverse(), None);
+    }
+
+    #[test]
+    fn z_order_and_visibility() {
+        let mut scene = Scene::new();
+        let root = scene.root();
+        let group = scene.add(
+            root,
+            Node::default().with_transform(Transform::translate(1., 1.)),
+        );
+        let blue = scene.add(group, Node::new(rect(2., 2., BLUE)).with_z_index(1));
+        let red = scene.add(group, Node::new(rect(3., 3., RED)));
+
+        let mut pixels = HeapPixels2D::new(4, 4, BLACK);
+        scene.render(&mut Canvas::new_entire(&mut pixels));
+        assert_eq!(pixels.pixels()[0], BLACK);
+        assert_eq!(pixels.pixels()[5], BLUE);
+        assert_eq!(pixels.pixels()[15], RED);
+        assert_eq!(scene.hit_test(1.5, 1.5), Some(blue));
+        assert_eq!(scene.hit_test(3.5, 3.5), Some(red));
+        assert_eq!(scene.hit_test(0.5, 0.5), None);
+
+        scene.node_mut(blue).unwrap().visible = false;
+        let mut pixels = HeapPixels2D::new(4, 4, BLACK);
+        scene.render(&mut Canvas::new_entire(&mut pixels));
+        assert_eq!(pixels.pixels()[5], RED);
+        assert_eq!(scene.hit_test(1.5, 1.5), Some(red));
+
+        assert_eq!(scene.remove(group).map(|n| n.content), Some(None));
+        assert_eq!(scene.node(red), None);
+        assert!(scene.children(root).is_empty());
+        assert_eq!(scene.remove(root), None);
+    }
+
+    #[test]
+    fn rotation_and_opacity() {
+        let mut scene = Scene::new();
+        let root = scene.root();
+        // A 4x1 bar rotated into a 1x4 column at x = 2
+        let bar = Node {
+            transform: Transform::rotate(FRAC_PI_2).then(&Transform::translate(3., 0.)),
+            opacity: 0.5,
+            ..Node::new(rect(4., 1., RED))
+        };
+        let bar = scene.add(root, bar);
+        assert_eq!(scene.hit_test(2.5, 3.5), Some(bar));
+        assert_eq!(scene.hit_test(3.5, 0.5), None);
+
+        let mut pixels = HeapPixels2D::new(4, 4, BLACK);
+        scene.render(&mut Canvas::new_entire(&mut pixels));
+        let half_red = Pixel::new(0xff, 0, 0, 0x80).over(BLACK);
+        for y in 0..4 {
+            assert_eq!(pixels.pixels()[y * 4 + 2], half_red);
+            assert_eq!(pixels.pixels()[y * 4 + 1], BLACK);
+        }
+    }
+
+    #[test]
+    fn image() {
+        let mut scene = Scene::new();
+        let root = scene.root();
+        let image = HeapPixels2D::from_pixels(2, 1, vec![RED, BLUE]);
+        let node = Node::new(Content::Image(image)).with_transform(Transform::scale(2., 2.));
+        scene.add(root, node);
+        let mut pixels = HeapPixels2D::new(4, 2, BLACK);
+        scene.render(&mut Canvas::new_entire(&mut pixels));
+        assert_eq!(
+            pixels.pixels(),
+            [RED, RED, BLUE, BLUE, RED, RED, BLUE, BLUE]
+        );
+    }
+
+    #[test]
+    fn anti_aliasing() {
+        let mut scene = Scene::new();
+        let root = scene.root();
+        scene.add(root, Node::new(rect(1.5, 1., RED)));
+        let mut pixels = HeapPixels2D::new(3, 1, BLACK);
+        scene.render(&mut Canvas::new_entire(&mut pixels));
+        let half_red = Pixel::new(0xff, 0, 0, 0x7f).over(BLACK);
+        assert_eq!(pixels.pixels(), [RED, half_red, BLACK]);
+    }
+
+    #[test]
+    fn text_cache() {
+        let font = crate::default_font();
+        let text = |text: &str| Content::Text {
+            text: text.to_string(),
+            font: &font,
+            size: 1,
+            color: RED,
+        };
+        let mut scene = Scene::new();
+        let root = scene.root();
+        let node = scene.add(root, Node::new(text("a")));
+        let render = |scene: &Scene<'_>| {
+            let mut pixels = HeapPixels2D::new(8, 8, BLACK);
+            scene.render(&mut Canvas::new_entire(&mut pixels));
+            pixels
+        };
+        let a = render(&scene);
+        assert_eq!(render(&scene), a);
+        assert_eq!(scene.text_images.0.borrow().len(), 1);
+
+        scene.node_mut(node).unwrap().content = Some(text("b"));
+        assert_ne!(render(&scene), a);
+
+        scene.node_mut(node).unwrap().content = Some(text(""));
+        assert_eq!(render(&scene), HeapPixels2D::new(8, 8, BLACK));
+        assert!(scene.text_images.0.borrow().is_empty());
+
+        scene.node_mut(node).unwrap().content = Some(text("a"));
+        render(&scene);
+        assert_eq!(scene.text_images.0.borrow().len(), 1);
+        scene.remove(node);
+        assert!(scene.text_images.0.borrow().is_empty());
+    }
+}
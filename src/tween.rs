@@ -0,0 +1,529 @@
+use std::f64::consts::PI;
+
+use crate::{Pixel, PixelPointF};
+
+/// Maps the progress of an animation from 0 to 1 to the progress of its value
+///
+/// - Ref: <https://easings.net/>
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    /// Overshoots below 0 before reaching 1
+    ElasticIn,
+    /// Overshoots above 1 before settling
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+    /// CSS `cubic-bezier(x1, y1, x2, y2)` from `(0, 0)` to `(1, 1)`; `x1` and `x2` must be in `0..=1`
+    ///
+    /// - Ref: <https://developer.mozilla.org/en-US/docs/Web/CSS/easing-function#cubic_b%C3%A9zier_easing_function>
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    /// `t` is clamped to `0..=1`; the result may leave it for elastic and bezier curves
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0., 1.);
+        match *self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t).powi(2),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4. * t.powi(3)
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+            Easing::ElasticIn => 1. - elastic_out(1. - t),
+            Easing::ElasticOut => elastic_out(t),
+            Easing::ElasticInOut => {
+                if t < 0.5 {
+                    (1. - elastic_out(1. - 2. * t)) / 2.
+                } else {
+                    (1. + elastic_out(2. * t - 1.)) / 2.
+                }
+            }
+            Easing::BounceIn => 1. - bounce_out(1. - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1. - bounce_out(1. - 2. * t)) / 2.
+                } else {
+                    (1. + bounce_out(2. * t - 1.)) / 2.
+                }
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+fn elastic_out(t: f64) -> f64 {
+    const C4: f64 = 2. * PI / 3.;
+    if t <= 0. || t >= 1. {
+        return t;
+    }
+    2f64.powf(-10. * t) * ((t * 10. - 0.75) * C4).sin() + 1.
+}
+
+fn bounce_out(t: f64) -> f64 {
+    const N1: f64 = 7.5625;
+    const D1: f64 = 2.75;
+    if t < 1. / D1 {
+        N1 * t * t
+    } else if t < 2. / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+/// Solve `x(s) = x` for the curve parameter `s` and return `y(s)`
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    // One coordinate of the curve with control values `p1` and `p2`
+    let curve = |p1: f64, p2: f64, s: f64| {
+        3. * (1. - s).powi(2) * s * p1 + 3. * (1. - s) * s * s * p2 + s.powi(3)
+    };
+    let slope = |p1: f64, p2: f64, s: f64| {
+        3. * (1. - s).powi(2) * p1 + 6. * (1. - s) * s * (p2 - p1) + 3. * s * s * (1. - p2)
+    };
+
+    // Newton's method, falling back to bisection on flat slopes
+    let mut s = x;
+    for _ in 0..8 {
+        let error = curve(x1, x2, s) - x;
+        if error.abs() < 1e-7 {
+            return curve(y1, y2, s);
+        }
+        let d = slope(x1, x2, s);
+        if d.abs() < 1e-6 {
+            break;
+        }
+        s -= error / d;
+    }
+    let (mut lo, mut hi) = (0., 1.);
+    s = x;
+    for _ in 0..50 {
+        let error = curve(x1, x2, s) - x;
+        if error.abs() < 1e-7 {
+            break;
+        }
+        if error > 0. {
+            hi = s;
+        } else {
+            lo = s;
+        }
+        s = (lo + hi) / 2.;
+    }
+    curve(y1, y2, s)
+}
+
+/// Values which can be interpolated by a [`Tween`]
+pub trait Lerp: Copy {
+    /// `self` at `t = 0` and `to` at `t = 1`; `t` may leave `0..=1` with overshooting easings
+    fn lerp(&self, to: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for PixelPointF {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        let (dx, dy) = self.f_to(*to);
+        PixelPointF::new(self.x().add_f(dx * t), self.y().add_f(dy * t))
+    }
+}
+
+/// Per channel, including alpha, clamped to the channel range
+impl Lerp for Pixel {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        let channel = |a: u8, b: u8| (a as f64).lerp(&(b as f64), t).round().clamp(0., 255.) as u8;
+        Pixel::new(
+            channel(self.r(), to.r()),
+            channel(self.g(), to.g()),
+            channel(self.b(), to.b()),
+            channel(self.a(), to.a()),
+        )
+    }
+}
+
+/// A value changing over a fixed duration, sampled at any time
+pub trait Animation {
+    type Value;
+
+    fn duration_ms(&self) -> f64;
+
+    /// `time_ms` is clamped to `0..=duration_ms`
+    fn value_at(&self, time_ms: f64) -> Self::Value;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub duration_ms: f64,
+    pub easing: Easing,
+}
+
+impl<T> Tween<T> {
+    pub fn new(from: T, to: T, duration_ms: f64, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration_ms,
+            easing,
+        }
+    }
+}
+
+impl<T> Animation for Tween<T>
+where
+    T: Lerp,
+{
+    type Value = T;
+
+    fn duration_ms(&self) -> f64 {
+        self.duration_ms
+    }
+
+    fn value_at(&self, time_ms: f64) -> T {
+        if self.duration_ms <= 0. {
+            return self.to;
+        }
+        let t = (time_ms / self.duration_ms).clamp(0., 1.);
+        self.from.lerp(&self.to, self.easing.apply(t))
+    }
+}
+
+/// Animations played one after another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence<A> {
+    animations: Vec<A>,
+}
+
+impl<A> Sequence<A>
+where
+    A: Animation,
+{
+    /// Panics if `animations` is empty
+    pub fn new(animations: Vec<A>) -> Self {
+        assert!(!animations.is_empty(), "a sequence needs an animation");
+        Self { animations }
+    }
+
+    pub fn animations(&self) -> &[A] {
+        &self.animations
+    }
+}
+
+impl<A> Animation for Sequence<A>
+where
+    A: Animation,
+{
+    type Value = A::Value;
+
+    fn duration_ms(&self) -> f64 {
+        self.animations.iter().map(|a| a.duration_ms()).sum()
+    }
+
+    fn value_at(&self, time_ms: f64) -> A::Value {
+        let mut start_ms = 0.;
+        let (last, rest) = self.animations.split_last().unwrap();
+        for animation in rest {
+            let end_ms = start_ms + animation.duration_ms();
+            if time_ms < end_ms {
+                return animation.value_at(time_ms - start_ms);
+            }
+            start_ms = end_ms;
+        }
+        last.value_at(time_ms - start_ms)
+    }
+}
+
+/// Two animations played at the same time; nest them for more
+///
+/// The shorter one holds its last value until the longer one ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parallel<A, B>(pub A, pub B);
+
+impl<A, B> Animation for Parallel<A, B>
+where
+    A: Animation,
+    B: Animation,
+{
+    type Value = (A::Value, B::Value);
+
+    fn duration_ms(&self) -> f64 {
+        self.0.duration_ms().max(self.1.duration_ms())
+    }
+
+    fn value_at(&self, time_ms: f64) -> Self::Value {
+        (self.0.value_at(time_ms), self.1.value_at(time_ms))
+    }
+}
+
+/// An animation played `times` times, reversing every other time with `yoyo`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Repeat<A> {
+    pub animation: A,
+    pub times: u32,
+    pub yoyo: bool,
+}
+
+impl<A> Animation for Repeat<A>
+where
+    A: Animation,
+{
+    type Value = A::Value;
+
+    fn duration_ms(&self) -> f64 {
+        self.animation.duration_ms() * self.times as f64
+    }
+
+    fn value_at(&self, time_ms: f64) -> A::Value {
+        let duration_ms = self.animation.duration_ms();
+        let time_ms = time_ms.clamp(0., self.duration_ms());
+        self.animation
+            .value_at(iteration_time(time_ms, duration_ms, self.yoyo))
+    }
+}
+
+/// Time within the iteration at `time_ms`, where the end of an iteration belongs to it rather than to the next one
+fn iteration_time(time_ms: f64, duration_ms: f64, yoyo: bool) -> f64 {
+    if duration_ms <= 0. {
+        return duration_ms;
+    }
+    let mut iteration = (time_ms / duration_ms).floor();
+    let mut local_ms = time_ms - iteration * duration_ms;
+    if local_ms == 0. && iteration > 0. {
+        iteration -= 1.;
+        local_ms = duration_ms;
+    }
+    if yoyo && iteration % 2. == 1. {
+        duration_ms - local_ms
+    } else {
+        local_ms
+    }
+}
+
+/// How many times a [`Player`] plays its animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Loop {
+    #[default]
+    Once,
+    Times(u32),
+    Forever,
+}
+
+/// Plays an [`Animation`] driven by the `dt_ms` of [`crate::Render::render`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player<A> {
+    animation: A,
+    elapsed_ms: f64,
+    repeat: Loop,
+    yoyo: bool,
+}
+
+impl<A> Player<A>
+where
+    A: Animation,
+{
+    pub fn new(animation: A) -> Self {
+        Self {
+            animation,
+            elapsed_ms: 0.,
+            repeat: Loop::Once,
+            yoyo: false,
+        }
+    }
+
+    pub fn with_loop(mut self, repeat: Loop) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Play every other iteration backwards
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn animation(&self) -> &A {
+        &self.animation
+    }
+
+    pub fn elapsed_ms(&self) -> f64 {
+        self.elapsed_ms
+    }
+
+    /// Advance the time, stopping at the end unless looping forever
+    pub fn update(&mut self, dt_ms: f64) {
+        self.elapsed_ms = (self.elapsed_ms + dt_ms.max(0.)).min(self.total_ms());
+    }
+
+    pub fn value(&self) -> A::Value {
+        let duration_ms = self.animation.duration_ms();
+        self.animation
+            .value_at(iteration_time(self.elapsed_ms, duration_ms, self.yoyo))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed_ms >= self.total_ms()
+    }
+
+    /// Play again from the start
+    pub fn reset(&mut self) {
+        self.elapsed_ms = 0.;
+    }
+
+    fn total_ms(&self) -> f64 {
+        let duration_ms = self.animation.duration_ms();
+        match self.repeat {
+            Loop::Once => duration_ms,
+            Loop::Times(times) => duration_ms * times as f64,
+            Loop::Forever => f64::INFINITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{a} != {b}");
+    }
+
+    #[test]
+    fn easings() {
+        let easings = [
+            Easing::Linear,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::ElasticIn,
+            Easing::ElasticOut,
+            Easing::ElasticInOut,
+            Easing::BounceIn,
+            Easing::BounceOut,
+            Easing::BounceInOut,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.),
+        ];
+        for easing in easings {
+            assert_close(easing.apply(0.), 0.);
+            assert_close(easing.apply(1.), 1.);
+        }
+        assert_close(Easing::QuadIn.apply(0.5), 0.25);
+        assert_close(Easing::CubicOut.apply(0.5), 0.875);
+        assert_close(Easing::BounceOut.apply(0.5), 0.765625);
+        assert!(Easing::ElasticOut.apply(0.2) > 1.);
+        // Linear and `ease` from CSS
+        assert_close(Easing::CubicBezier(0., 0., 1., 1.).apply(0.3), 0.3);
+        assert!((Easing::CubicBezier(0.25, 0.1, 0.25, 1.).apply(0.5) - 0.8024).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tweens() {
+        let tween = Tween::new(10., 20., 100., Easing::Linear);
+        assert_eq!(tween.value_at(-5.), 10.);
+        assert_eq!(tween.value_at(25.), 12.5);
+        assert_eq!(tween.value_at(500.), 20.);
+
+        let black = Pixel::new(0, 0, 0, 0xff);
+        let white = Pixel::new(0xff, 0xff, 0xff, 0xff);
+        let tween = Tween::new(black, white, 100., Easing::Linear);
+        assert_eq!(tween.value_at(50.), Pixel::new(0x80, 0x80, 0x80, 0xff));
+        let overshoot = Tween::new(black, white, 100., Easing::ElasticOut);
+        assert_eq!(overshoot.value_at(20.), white);
+
+        let tween = Tween::new(
+            PixelPointF::from_int(0, 10),
+            PixelPointF::from_int(10, 0),
+            100.,
+            Easing::Linear,
+        );
+        assert_eq!(tween.value_at(25.), PixelPointF::from_float(2, 0.5, 7, 0.5));
+    }
+
+    #[test]
+    fn groups() {
+        let sequence = Sequence::new(vec![
+            Tween::new(0., 1., 100., Easing::Linear),
+            Tween::new(1., 0., 50., Easing::Linear),
+        ]);
+        assert_eq!(sequence.duration_ms(), 150.);
+        assert_eq!(sequence.value_at(50.), 0.5);
+        assert_eq!(sequence.value_at(100.), 1.);
+        assert_eq!(sequence.value_at(125.), 0.5);
+        assert_eq!(sequence.value_at(200.), 0.);
+
+        let parallel = Parallel(sequence, Tween::new(0., 10., 50., Easing::Linear));
+        assert_eq!(parallel.duration_ms(), 150.);
+        assert_eq!(parallel.value_at(25.), (0.25, 5.));
+        assert_eq!(parallel.value_at(125.), (0.5, 10.));
+
+        let repeat = Repeat {
+            animation: Tween::new(0., 1., 100., Easing::Linear),
+            times: 3,
+            yoyo: true,
+        };
+        assert_eq!(repeat.duration_ms(), 300.);
+        assert_eq!(repeat.value_at(125.), 0.75);
+        assert_eq!(repeat.value_at(200.), 0.);
+        assert_eq!(repeat.value_at(300.), 1.);
+    }
+
+    #[test]
+    fn player() {
+        let tween = Tween::new(0., 1., 100., Easing::Linear);
+        let mut player = Player::new(tween);
+        player.update(60.);
+        assert_eq!(player.value(), 0.6);
+        player.update(60.);
+        assert!(player.is_finished());
+        assert_eq!(player.value(), 1.);
+
+        let mut player = Player::new(tween).with_loop(Loop::Forever).with_yoyo(true);
+        player.update(150.);
+        assert_eq!(player.value(), 0.5);
+        player.update(75.);
+        assert_eq!(player.value(), 0.25);
+        assert!(!player.is_finished());
+
+        let mut player = Player::new(tween).with_loop(Loop::Times(2));
+        player.update(1000.);
+        assert!(player.is_finished());
+        assert_eq!(player.value(), 1.);
+        player.reset();
+        assert_eq!(player.value(), 0.);
+    }
+}
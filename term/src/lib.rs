@@ -0,0 +1,386 @@
+//! Print pixels to a terminal with ANSI colors.
+//!
+//! Each character cell is an upper half block `▀` showing two pixels:
+//! the top one in the foreground color and the bottom one in the background color.
+
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use olive_rs::{Pixel, Pixels2D, Render};
+
+const UPPER_HALF_BLOCK: char = '▀';
+const DEFAULT_COLUMNS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// 24-bit colors
+    TrueColor,
+    /// The xterm 256-color palette
+    Ansi256,
+}
+
+impl ColorMode {
+    /// True color if `COLORTERM` says so, the 256-color palette otherwise
+    ///
+    /// - Ref: <https://github.com/termstandard/colors#checking-for-colorterm>
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor") | Ok("24bit") => ColorMode::TrueColor,
+            _ => ColorMode::Ansi256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermOptions {
+    pub color_mode: ColorMode,
+    /// Images wider than this many columns are downscaled
+    pub max_columns: usize,
+    /// Translucent pixels are composited over this color
+    pub background: Pixel,
+}
+
+/// The 256-color palette and 80 columns, without looking at the terminal
+impl Default for TermOptions {
+    fn default() -> Self {
+        Self {
+            color_mode: ColorMode::Ansi256,
+            max_columns: DEFAULT_COLUMNS,
+            background: Pixel::new(0, 0, 0, u8::MAX),
+        }
+    }
+}
+
+impl TermOptions {
+    /// The color mode and width of the current terminal
+    ///
+    /// Runs `stty`, see [`terminal_columns`].
+    pub fn detect() -> Self {
+        Self {
+            color_mode: ColorMode::detect(),
+            max_columns: terminal_columns(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Width of the terminal from `stty size` or `COLUMNS`, or 80 if neither is available
+pub fn terminal_columns() -> usize {
+    fn stty_columns() -> Option<usize> {
+        let tty = std::fs::File::open("/dev/tty").ok()?;
+        let output = std::process::Command::new("stty")
+            .arg("size")
+            .stdin(tty)
+            .output()
+            .ok()?;
+        let output = String::from_utf8(output.stdout).ok()?;
+        let (_rows, columns) = output.trim().split_once(' ')?;
+        columns.parse().ok()
+    }
+
+    stty_columns()
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|columns| *columns > 0)
+        .unwrap_or(DEFAULT_COLUMNS)
+}
+
+pub fn print_pixels<CP>(pixels: &CP, options: &TermOptions) -> io::Result<()>
+where
+    CP: Pixels2D,
+{
+    let mut stdout = io::stdout().lock();
+    write_pixels(pixels, options, &mut stdout)?;
+    stdout.flush()
+}
+
+pub fn write_pixels<CP, W>(pixels: &CP, options: &TermOptions, w: &mut W) -> io::Result<()>
+where
+    CP: Pixels2D,
+    W: Write,
+{
+    write_pixel_slice(pixels.pixels(), pixels.width(), pixels.height(), options, w)
+}
+
+fn write_pixel_slice<W>(
+    pixels: &[Pixel],
+    width: usize,
+    height: usize,
+    options: &TermOptions,
+    w: &mut W,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let (columns, rows, opaque) = downscale(pixels, width, height, options);
+    let mut out = String::new();
+    for y in (0..rows).step_by(2) {
+        for x in 0..columns {
+            let top = opaque[y * columns + x];
+            push_color(&mut out, top, Layer::Foreground, options.color_mode);
+            if y + 1 < rows {
+                let bottom = opaque[(y + 1) * columns + x];
+                push_color(&mut out, bottom, Layer::Background, options.color_mode);
+            } else {
+                // The default background below the last odd row
+                out.push_str("\x1b[49m");
+            }
+            out.push(UPPER_HALF_BLOCK);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    w.write_all(out.as_bytes())
+}
+
+/// Box-filter the pixels down to at most `max_columns` wide and drop alpha against the background
+fn downscale(
+    pixels: &[Pixel],
+    width: usize,
+    height: usize,
+    options: &TermOptions,
+) -> (usize, usize, Vec<Pixel>) {
+    let columns = width.min(options.max_columns.max(1));
+    if columns == 0 || height == 0 {
+        return (0, 0, Vec::new());
+    }
+    let rows = ((height * columns) as f64 / width as f64).round().max(1.) as usize;
+
+    let mut out = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        let y_range = row * height / rows..((row + 1) * height / rows).max(row * height / rows + 1);
+        for column in 0..columns {
+            let x_range = column * width / columns
+                ..((column + 1) * width / columns).max(column * width / columns + 1);
+            let mut sum = [0u64; 3];
+            let mut n = 0;
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let p = pixels[y * width + x].over(options.background);
+                    sum[0] += p.r() as u64;
+                    sum[1] += p.g() as u64;
+                    sum[2] += p.b() as u64;
+                    n += 1;
+                }
+            }
+            let [r, g, b] = sum.map(|s| (s / n) as u8);
+            out.push(Pixel::new(r, g, b, u8::MAX));
+        }
+    }
+    (columns, rows, out)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Layer {
+    Foreground,
+    Background,
+}
+
+/// - Ref: <https://en.wikipedia.org/wiki/ANSI_escape_code#Colors>
+fn push_color(out: &mut String, color: Pixel, layer: Layer, mode: ColorMode) {
+    let code = match layer {
+        Layer::Foreground => 38,
+        Layer::Background => 48,
+    };
+    let color = match mode {
+        ColorMode::TrueColor => format!("2;{};{};{}", color.r(), color.g(), color.b()),
+        ColorMode::Ansi256 => format!("5;{}", ansi_256(color)),
+    };
+    out.push_str(&format!("\x1b[{code};{color}m"));
+}
+
+/// The closest color of the 6x6x6 cube or the gray ramp
+fn ansi_256(color: Pixel) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    fn nearest_level(c: u8) -> usize {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(c))
+            .unwrap()
+    }
+
+    fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+        (0..3).map(|i| (a[i].abs_diff(b[i]) as u32).pow(2)).sum()
+    }
+
+    let rgb = [color.r(), color.g(), color.b()];
+    let cube = rgb.map(nearest_level);
+    let cube_rgb = cube.map(|i| CUBE_LEVELS[i]);
+    let cube_index = 16 + 36 * cube[0] + 6 * cube[1] + cube[2];
+
+    // Grays from 8 to 238 in steps of 10
+    let mean = (rgb.iter().map(|c| *c as u32).sum::<u32>() / 3) as u8;
+    let gray_step = (mean.saturating_sub(3) / 10).min(23);
+    let gray = 8 + 10 * gray_step;
+    let gray_index = 232 + gray_step as usize;
+
+    if distance(rgb, [gray; 3]) < distance(rgb, cube_rgb) {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunOptions {
+    /// Must be positive and large enough for the frame time to fit in a [`Duration`]
+    pub fps: f64,
+    /// Stop after this many frames or run forever
+    pub frames: Option<usize>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            fps: 30.,
+            frames: None,
+        }
+    }
+}
+
+/// Animate `render` in place on the terminal.
+///
+/// `width` and `height` are the size of [`Render::pixels`].
+/// The first frame is rendered with a `dt_ms` of 0 like in the `wasm` runner.
+pub fn run_render<R>(
+    width: usize,
+    height: usize,
+    render: &mut R,
+    options: &TermOptions,
+    run_options: &RunOptions,
+) -> io::Result<()>
+where
+    R: Render,
+{
+    // Also fails for 0, negative and NaN fps
+    let frame_time = Duration::try_from_secs_f64(1. / run_options.fps).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid fps {}", run_options.fps),
+        )
+    })?;
+    let mut stdout = io::stdout().lock();
+    // Clear the screen and hide the cursor
+    stdout.write_all(b"\x1b[2J\x1b[?25l")?;
+
+    let result = (|| {
+        let mut last_frame: Option<Instant> = None;
+        let mut frame = 0;
+        while run_options.frames.is_none_or(|frames| frame < frames) {
+            let now = Instant::now();
+            let dt_ms = match last_frame {
+                Some(t) => now.duration_since(t).as_secs_f64() * 1000.,
+                None => 0.,
+            };
+            last_frame = Some(now);
+
+            render.update(dt_ms);
+            render.render(dt_ms);
+            let pixels = render.pixels();
+            if pixels.len() != width * height {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the number of pixels does not match the size",
+                ));
+            }
+            // Draw over the previous frame from the top-left corner
+            stdout.write_all(b"\x1b[H")?;
+            write_pixel_slice(pixels, width, height, options, &mut stdout)?;
+            stdout.flush()?;
+
+            frame += 1;
+            if let Some(rest) = frame_time.checked_sub(now.elapsed()) {
+                std::thread::sleep(rest);
+            }
+        }
+        Ok(())
+    })();
+
+    // Show the cursor again
+    stdout.write_all(b"\x1b[?25h")?;
+    stdout.flush()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use olive_rs::HeapPixels2D;
+
+    use super::*;
+
+    fn options(color_mode: ColorMode, max_columns: usize) -> TermOptions {
+        TermOptions {
+            color_mode,
+            max_columns,
+            background: Pixel::new(0, 0, 0, u8::MAX),
+        }
+    }
+
+    fn render(pixels: &HeapPixels2D, options: &TermOptions) -> String {
+        let mut bytes = Vec::new();
+        write_pixels(pixels, options, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn half_blocks() {
+        let red = Pixel::new(0xff, 0, 0, 0xff);
+        let blue = Pixel::new(0, 0, 0xff, 0xff);
+        let pixels = HeapPixels2D::from_pixels(1, 3, vec![red, blue, red]);
+        assert_eq!(
+            render(&pixels, &options(ColorMode::TrueColor, 80)),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀\x1b[0m\n\x1b[38;2;255;0;0m\x1b[49m▀\x1b[0m\n"
+        );
+        assert_eq!(
+            render(&pixels, &options(ColorMode::Ansi256, 80)),
+            "\x1b[38;5;196m\x1b[48;5;21m▀\x1b[0m\n\x1b[38;5;196m\x1b[49m▀\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn downscale_and_alpha() {
+        let white = Pixel::new(0xff, 0xff, 0xff, 0xff);
+        let clear = Pixel::new(0xff, 0xff, 0xff, 0);
+        let pixels = HeapPixels2D::from_pixels(
+            4,
+            2,
+            vec![white, clear, white, white, white, clear, white, white],
+        );
+        let (columns, rows, opaque) =
+            downscale(pixels.pixels(), 4, 2, &options(ColorMode::TrueColor, 2));
+        assert_eq!((columns, rows), (2, 1));
+        assert_eq!(opaque, [Pixel::new(127, 127, 127, 0xff), white]);
+    }
+
+    #[test]
+    fn ansi_256_palette() {
+        assert_eq!(ansi_256(Pixel::new(0, 0, 0, 0xff)), 16);
+        assert_eq!(ansi_256(Pixel::new(0xff, 0xff, 0xff, 0xff)), 231);
+        assert_eq!(ansi_256(Pixel::new(0x80, 0x80, 0x80, 0xff)), 244);
+        assert_eq!(ansi_256(Pixel::new(0, 0xff, 0, 0xff)), 46);
+    }
+
+    #[test]
+    fn invalid_fps() {
+        struct Blank(Vec<Pixel>);
+
+        impl Render for Blank {
+            fn render(&mut self, _dt_ms: f64) {}
+
+            fn pixels(&self) -> &[Pixel] {
+                &self.0
+            }
+        }
+
+        let mut render = Blank(vec![Pixel::new(0, 0, 0, u8::MAX)]);
+        for fps in [0., -1., f64::NAN, 1e-300] {
+            let run_options = RunOptions {
+                fps,
+                frames: Some(1),
+            };
+            let error =
+                run_render(1, 1, &mut render, &TermOptions::default(), &run_options).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -1,45 +1,18 @@
 #[cfg(test)]
 mod tests {
-    use std::{io::Read, path::Path};
-
-    use file_gen::{save_to_png_stream, save_to_ppm_stream};
-    use olive_rs::{default_font, Canvas, HeapPixels2D, Pixel, PixelPoint, PixelPointF, Pixels2D};
+    use file_gen::GoldenTest;
+    use olive_rs::{
+        default_font, Canvas, CanvasError, DirtyRect, GlyphCache, HeapPixels2D, Pixel, PixelPoint,
+        PixelPointF, Pixels2D, TextOutline, TextShadow, TextSpan, TextStyle,
+    };
 
     const BACKGROUND_COLOR: Pixel = Pixel::new(0x20, 0x20, 0x20, 0xff);
     const RED_COLOR: Pixel = Pixel::new(0xff, 0, 0, 0xff);
     const GREEN_COLOR: Pixel = Pixel::new(0, 0xff, 0, 0xff);
     const BLUE_COLOR: Pixel = Pixel::new(0, 0, 0xff, 0xff);
 
-    fn assert_eq_bytes_with_file<P>(expected: P, actual: &[u8])
-    where
-        P: AsRef<Path>,
-    {
-        let file_path = expected.as_ref();
-        let mut file = std::fs::File::open(file_path).unwrap();
-        let mut expected = Vec::new();
-        file.read_to_end(&mut expected).unwrap();
-        assert_eq!(actual, expected);
-    }
-
-    fn assert_eq_ppm_pixels_with_file<P, CP>(expected: P, actual: &CP)
-    where
-        P: AsRef<Path>,
-        CP: Pixels2D,
-    {
-        let mut bytes = Vec::new();
-        save_to_ppm_stream(actual, &mut bytes).unwrap();
-        assert_eq_bytes_with_file(expected, &bytes);
-    }
-
-    #[allow(unused)]
-    fn assert_eq_png_pixels_with_file<P, CP>(expected: P, actual: &CP)
-    where
-        P: AsRef<Path>,
-        CP: Pixels2D,
-    {
-        let mut bytes = Vec::new();
-        save_to_png_stream(actual, &mut bytes).unwrap();
-        assert_eq_bytes_with_file(expected, &bytes);
+    fn golden() -> GoldenTest {
+        GoldenTest::new(env!("CARGO_TARGET_TMPDIR"))
     }
 
     #[test]
@@ -69,7 +42,7 @@ mod tests {
             };
             canvas.fill_pixel_rect(p, w / 2, h / 2, BLUE_COLOR);
         }
-        assert_eq_ppm_pixels_with_file("tests/assets/fill_rect.ppm", &pixels);
+        golden().assert("tests/assets/fill_rect.png", &pixels);
     }
 
     #[test]
@@ -110,7 +83,7 @@ mod tests {
             let r = (-w / 4) as f64;
             canvas.fill_pixel_circle(c, r, GREEN_COLOR);
         }
-        assert_eq_ppm_pixels_with_file("tests/assets/fill_circle.ppm", &pixels);
+        golden().assert("tests/assets/fill_circle.png", &pixels);
     }
 
     #[test]
@@ -152,7 +125,7 @@ mod tests {
             let p2 = PixelPoint { x: w / 2, y: h };
             canvas.draw_pixel_line(p1, p2, GREEN_COLOR);
         }
-        assert_eq_ppm_pixels_with_file("tests/assets/draw_line.ppm", &pixels);
+        golden().assert("tests/assets/draw_line.png", &pixels);
     }
 
     #[test]
@@ -182,7 +155,7 @@ mod tests {
             let v3 = PixelPointF::from_int(w * 3 / 8, h * 3 / 8);
             canvas.fill_pixel_triangle(v1, v2, v3, BLUE_COLOR);
         }
-        assert_eq_ppm_pixels_with_file("tests/assets/fill_triangle.ppm", &pixels);
+        golden().assert("tests/assets/fill_triangle.png", &pixels);
     }
 
     #[test]
@@ -213,7 +186,7 @@ mod tests {
             let v3 = PixelPointF::from_int(w / 2, 0);
             canvas.fill_pixel_triangle(v1, v2, v3, Pixel::new(0xaa, 0xaa, 0, 0xbb));
         }
-        assert_eq_ppm_pixels_with_file("tests/assets/alpha_blending.ppm", &pixels);
+        golden().assert("tests/assets/alpha_blending.png", &pixels);
     }
 
     #[test]
@@ -240,6 +213,289 @@ Hello, world!
         let pos = PixelPoint { x: -16, y: 32 * 6 };
         canvas.pixel_text(text, pos, &font, 14, GREEN_COLOR);
 
-        assert_eq_ppm_pixels_with_file("tests/assets/text.ppm", &pixels);
+        golden().assert("tests/assets/text.png", &pixels);
+    }
+
+    #[test]
+    fn rotated_text_without_angle() {
+        let w = 128;
+        let h = 64;
+        let font = default_font();
+        let text = "Hello,\nworld!";
+
+        let mut expected = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut expected);
+        canvas.pixel_text(text, PixelPoint { x: 3, y: 2 }, &font, 3, RED_COLOR);
+
+        let mut actual = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut actual);
+        let pos = PixelPointF::from_int(3, 2);
+        canvas.pixel_text_rotated(text, pos, 0., &font, 3, RED_COLOR);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn rotated_text() {
+        let w = 256;
+        let h = 256;
+        let mut pixels = HeapPixels2D::new(w, h, Pixel::new(0, 0, 0, 0));
+        let mut canvas = Canvas::new_entire(&mut pixels);
+        canvas.fill(BACKGROUND_COLOR);
+        let font = default_font();
+        let c = PixelPointF::from_int(w as isize / 2, h as isize / 2);
+        for i in 0..8 {
+            let angle = std::f64::consts::PI / 4. * i as f64;
+            canvas.pixel_text_rotated("Olive", c, angle, &font, 3, RED_COLOR);
+        }
+
+        // Half circle
+        let path = (0..=32)
+            .map(|i| {
+                let angle = std::f64::consts::PI * (1. + i as f64 / 32.);
+                let x = (w / 2) as f64 + angle.cos() * 100.;
+                let y = (h / 2) as f64 + angle.sin() * 100.;
+                PixelPointF::from_float(0, x, 0, y)
+            })
+            .collect::<Vec<_>>();
+        canvas.pixel_text_on_path("Hello, world!", &path, 20., &font, 4, GREEN_COLOR);
+
+        golden().assert("tests/assets/rotated_text.png", &pixels);
+    }
+
+    #[test]
+    fn text_on_path_edges() {
+        let font = default_font();
+        let size = 2;
+        let w = (font.glyph_or_unknown('A').width() * size) as f64;
+        let advance = ((font.glyph_or_unknown('A').width() + 1) * size) as f64;
+        let path = [
+            PixelPointF::from_int(10, 20),
+            PixelPointF::from_int(110, 20),
+        ];
+        let render = |text: &str, offset: f64| {
+            let mut pixels = HeapPixels2D::new(128, 32, Pixel::new(0, 0, 0, 0));
+            Canvas::new_entire(&mut pixels)
+                .pixel_text_on_path(text, &path, offset, &font, size, RED_COLOR);
+            pixels
+        };
+
+        // Before the start, only the glyphs partly off the path are skipped
+        assert_eq!(render("AA", -1.), render("A", advance - 1.));
+        // The last glyph would run past the end
+        let offset = 100. - advance - w / 2.;
+        assert_eq!(render("AA", offset), render("A", offset));
+        assert_ne!(render("A", offset), render("", offset));
+    }
+
+    #[test]
+    fn cached_text() {
+        let w = 128;
+        let h = 64;
+        let font = default_font();
+        let text = "Hello,\nworld!\n// Out of canvas";
+
+        let mut expected = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut expected);
+        canvas.pixel_text(text, PixelPoint { x: -5, y: 2 }, &font, 3, RED_COLOR);
+
+        let mut actual = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut actual);
+        let mut cache = GlyphCache::new(&font);
+        canvas.pixel_text_cached(text, PixelPoint { x: -5, y: 2 }, &mut cache, 3, RED_COLOR);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn styled_text() {
+        let w = 256;
+        let h = 192;
+        let mut pixels = HeapPixels2D::new(w, h, Pixel::new(0, 0, 0, 0));
+        let mut canvas = Canvas::new_entire(&mut pixels);
+        canvas.fill(BACKGROUND_COLOR);
+        let font = default_font();
+        let mut cache = GlyphCache::new(&font);
+        let styles = [
+            TextStyle {
+                bold: true,
+                ..Default::default()
+            },
+            TextStyle {
+                underline: true,
+                strikethrough: true,
+                ..Default::default()
+            },
+            TextStyle {
+                outline: Some(TextOutline {
+                    width: 2,
+                    color: BLUE_COLOR,
+                }),
+                ..Default::default()
+            },
+            TextStyle {
+                bold: true,
+                underline: true,
+                shadow: Some(TextShadow {
+                    offset: PixelPoint { x: 3, y: 3 },
+                    color: Pixel::new(0, 0, 0, 0x88),
+                }),
+                ..Default::default()
+            },
+        ];
+        for (i, style) in styles.iter().enumerate() {
+            let pos = PixelPoint {
+                x: 8,
+                y: 8 + 44 * i as isize,
+            };
+            canvas.pixel_text_styled("Olive\nrs", pos, &mut cache, 3, RED_COLOR, style);
+        }
+        golden().assert("tests/assets/styled_text.png", &pixels);
+    }
+
+    #[test]
+    fn translucent_shadow() {
+        let w = 128;
+        let h = 96;
+        let mut pixels = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut pixels);
+        let font = default_font();
+        let mut cache = GlyphCache::new(&font);
+        let style = TextStyle {
+            bold: true,
+            underline: true,
+            outline: Some(TextOutline {
+                width: 2,
+                color: BLUE_COLOR,
+            }),
+            shadow: Some(TextShadow {
+                offset: PixelPoint { x: 0, y: 48 },
+                color: Pixel::new(0, 0, 0, 0x80),
+            }),
+            ..Default::default()
+        };
+        canvas.pixel_text_styled(
+            "AWA",
+            PixelPoint { x: 8, y: 4 },
+            &mut cache,
+            3,
+            RED_COLOR,
+            &style,
+        );
+
+        // Overlapping outlines, glyphs and underline don't darken the shadow
+        let shadow = pixels.pixels()[48 * w..]
+            .iter()
+            .filter(|&&p| p != BACKGROUND_COLOR)
+            .collect::<Vec<_>>();
+        assert!(!shadow.is_empty());
+        assert!(shadow.iter().all(|&&p| p == *shadow[0]));
+    }
+
+    #[test]
+    fn rich_text() {
+        let w = 256;
+        let h = 128;
+        let mut pixels = HeapPixels2D::new(w, h, Pixel::new(0, 0, 0, 0));
+        let mut canvas = Canvas::new_entire(&mut pixels);
+        canvas.fill(BACKGROUND_COLOR);
+        let font = default_font();
+        let bold = TextStyle {
+            bold: true,
+            ..Default::default()
+        };
+        let spans = [
+            TextSpan::new("OK: ", &font, 2, GREEN_COLOR),
+            TextSpan::new("12", &font, 3, GREEN_COLOR).with_style(bold),
+            TextSpan::new("  FAIL: ", &font, 2, RED_COLOR),
+            TextSpan::new("3", &font, 3, RED_COLOR).with_style(bold),
+            TextSpan::new(
+                "\nThe quick brown fox jumps over the ",
+                &font,
+                2,
+                BLUE_COLOR,
+            ),
+            TextSpan::new("lazy", &font, 2, BLUE_COLOR).with_style(TextStyle {
+                underline: true,
+                ..Default::default()
+            }),
+            TextSpan::new(" dog", &font, 2, BLUE_COLOR),
+        ];
+        canvas.pixel_rich_text(&spans, PixelPoint { x: 4, y: 4 }, Some(w - 8));
+        golden().assert("tests/assets/rich_text.png", &pixels);
+    }
+
+    #[test]
+    fn rich_text_with_one_span() {
+        let w = 128;
+        let h = 64;
+        let font = default_font();
+        let text = "Hello,\n\nworld!";
+
+        let mut expected = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut expected);
+        canvas.pixel_text(text, PixelPoint { x: 3, y: 2 }, &font, 2, RED_COLOR);
+
+        let mut actual = HeapPixels2D::new(w, h, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new_entire(&mut actual);
+        let spans = [TextSpan::new(text, &font, 2, RED_COLOR)];
+        canvas.pixel_rich_text(&spans, PixelPoint { x: 3, y: 2 }, None);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn try_new_and_pixel_mut() {
+        let mut pixels = HeapPixels2D::new(4, 3, BACKGROUND_COLOR);
+        assert_eq!(
+            Canvas::try_new(&mut pixels, 1..5, 0..3),
+            Err(CanvasError::XRangeOutOfBounds {
+                range: 1..5,
+                width: 4
+            })
+        );
+        assert_eq!(
+            Canvas::try_new(&mut pixels, 0..4, 3..3),
+            Err(CanvasError::YRangeOutOfBounds {
+                range: 3..3,
+                height: 3
+            })
+        );
+        // Empty ranges inside the pixels are fine
+        assert_eq!(Canvas::try_new(&mut pixels, 1..1, 0..3).unwrap().width(), 0);
+
+        let mut canvas = Canvas::try_new(&mut pixels, 1..3, 1..3).unwrap();
+        *canvas.try_pixel_mut(1, 1).unwrap() = RED_COLOR;
+        assert_eq!(
+            canvas.try_pixel_mut(2, 0),
+            Err(CanvasError::PixelOutOfBounds {
+                x: 2,
+                y: 0,
+                width: 2,
+                height: 2
+            })
+        );
+        assert_eq!(pixels.pixels()[2 * 4 + 2], RED_COLOR);
+    }
+
+    #[test]
+    fn dirty_rect() {
+        let mut pixels = HeapPixels2D::new(16, 16, BACKGROUND_COLOR);
+        let mut canvas = Canvas::new(&mut pixels, 4..12, 4..12);
+        assert!(canvas.dirty_rect().is_empty());
+
+        canvas.fill_pixel_rect(PixelPoint { x: 1, y: 2 }, 3, 2, RED_COLOR);
+        assert_eq!(canvas.dirty_rect(), DirtyRect::new(5, 6, 3, 2));
+        canvas.draw_pixel_line(
+            PixelPoint { x: 7, y: 7 },
+            PixelPoint { x: 7, y: 7 },
+            GREEN_COLOR,
+        );
+        assert_eq!(canvas.take_dirty_rect(), DirtyRect::new(5, 6, 7, 6));
+        assert!(canvas.dirty_rect().is_empty());
+
+        canvas.mark_dirty(DirtyRect::new(0, 0, 1, 1));
+        canvas.fill(BLUE_COLOR);
+        assert_eq!(canvas.dirty_rect(), DirtyRect::new(0, 0, 16, 16));
     }
 }
@@ -0,0 +1,269 @@
+use olive_rs::{Render, StatsOverlay};
+
+/// Allowed early arrival of an animation frame under [`LoopOptions::max_fps`]
+const FRAME_CAP_SLACK_MS: f64 = 1.;
+
+/// `dt_ms` of [`crate::RenderController::step`] without a fixed timestep
+pub(crate) const DEFAULT_STEP_MS: f64 = 1000. / 60.;
+/// Assumed display refresh rate for [`olive_rs::FrameStats`] without [`LoopOptions::max_fps`]
+const DEFAULT_REFRESH_RATE: f64 = 60.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopOptions {
+    /// Call [`Render::update`] with this `dt_ms` as many times as the elapsed time allows,
+    /// then [`Render::render`] once, instead of both once per animation frame with the elapsed time.
+    ///
+    /// Values which are not positive are replaced by 1/60 s.
+    pub fixed_dt_ms: Option<f64>,
+    /// At most this many fixed updates per frame; the time left over is dropped so the loop
+    /// does not try to catch up forever after a hidden tab or a slow frame.
+    ///
+    /// 0 is raised to 1.
+    pub max_updates_per_frame: u32,
+    /// Skip animation frames to stay at or below this rate; ignored if not positive
+    pub max_fps: Option<f64>,
+    /// Draw [`crate::RenderController::stats`] over each frame
+    pub stats_overlay: Option<StatsOverlay>,
+}
+
+impl Default for LoopOptions {
+    fn default() -> Self {
+        Self {
+            fixed_dt_ms: None,
+            max_updates_per_frame: 5,
+            max_fps: None,
+            stats_overlay: None,
+        }
+    }
+}
+
+impl LoopOptions {
+    /// Replace the values which would stop the loop from ever rendering
+    pub(crate) fn validated(self) -> Self {
+        let positive = |v: &f64| *v > 0.;
+        Self {
+            fixed_dt_ms: self
+                .fixed_dt_ms
+                .map(|dt| Some(dt).filter(positive).unwrap_or(DEFAULT_STEP_MS)),
+            max_updates_per_frame: self.max_updates_per_frame.max(1),
+            max_fps: self.max_fps.filter(positive),
+            ..self
+        }
+    }
+
+    /// Expected time between drawn frames
+    ///
+    /// Frames without updates are skipped, so a fixed timestep slower than the frame rate sets the pace.
+    pub(crate) fn target_frame_ms(&self) -> f64 {
+        let options = self.validated();
+        let frame_ms = 1000. / options.max_fps.unwrap_or(DEFAULT_REFRESH_RATE);
+        frame_ms.max(options.fixed_dt_ms.unwrap_or(0.))
+    }
+}
+
+/// Updates to run for an animation frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Frame {
+    pub dt_ms: f64,
+    /// Nothing is rendered nor drawn for 0
+    pub updates: u32,
+}
+
+impl Frame {
+    /// Run each update, then draw once with their total time
+    pub fn run<R>(&self, render: &mut R)
+    where
+        R: Render + ?Sized,
+    {
+        for _ in 0..self.updates {
+            render.update(self.dt_ms);
+        }
+        render.render(self.dt_ms * self.updates as f64);
+    }
+}
+
+/// Turns animation frame timestamps into updates following [`LoopOptions`]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FrameTimer {
+    options: LoopOptions,
+    last_frame_ms: Option<f64>,
+    accumulator_ms: f64,
+}
+
+impl FrameTimer {
+    pub fn new(options: LoopOptions) -> Self {
+        Self {
+            options: options.validated(),
+            last_frame_ms: None,
+            accumulator_ms: 0.,
+        }
+    }
+
+    /// The next frame starts over like the first one, e.g. after a pause
+    pub fn reset(&mut self) {
+        self.last_frame_ms = None;
+        self.accumulator_ms = 0.;
+    }
+
+    /// The first frame is rendered with a `dt_ms` of 0, or one fixed update
+    pub fn frame(&mut self, timestamp_ms: f64) -> Frame {
+        let elapsed_ms = match self.last_frame_ms {
+            Some(t) => {
+                let elapsed_ms = timestamp_ms - t;
+                if let Some(max_fps) = self.options.max_fps {
+                    if elapsed_ms + FRAME_CAP_SLACK_MS < 1000. / max_fps {
+                        return Frame {
+                            dt_ms: 0.,
+                            updates: 0,
+                        };
+                    }
+                }
+                Some(elapsed_ms)
+            }
+            None => None,
+        };
+        self.last_frame_ms = Some(timestamp_ms);
+
+        let Some(fixed_dt_ms) = self.options.fixed_dt_ms else {
+            return Frame {
+                dt_ms: elapsed_ms.unwrap_or(0.),
+                updates: 1,
+            };
+        };
+        self.accumulator_ms += elapsed_ms.unwrap_or(fixed_dt_ms);
+        let mut updates = 0;
+        while self.accumulator_ms >= fixed_dt_ms && updates < self.options.max_updates_per_frame {
+            self.accumulator_ms -= fixed_dt_ms;
+            updates += 1;
+        }
+        if updates == self.options.max_updates_per_frame {
+            self.accumulator_ms %= fixed_dt_ms;
+        }
+        Frame {
+            dt_ms: fixed_dt_ms,
+            updates,
+        }
+    }
+
+    /// `dt_ms` of a single step while paused
+    pub fn step_dt_ms(&self) -> f64 {
+        self.options.fixed_dt_ms.unwrap_or(DEFAULT_STEP_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updates(timer: &mut FrameTimer, timestamps: &[f64]) -> Vec<u32> {
+        timestamps.iter().map(|t| timer.frame(*t).updates).collect()
+    }
+
+    #[test]
+    fn variable() {
+        let mut timer = FrameTimer::new(LoopOptions::default());
+        assert_eq!(timer.frame(100.).dt_ms, 0.);
+        assert_eq!(
+            timer.frame(116.),
+            Frame {
+                dt_ms: 16.,
+                updates: 1
+            }
+        );
+        timer.reset();
+        assert_eq!(timer.frame(500.).dt_ms, 0.);
+    }
+
+    #[test]
+    fn fixed_with_catch_up() {
+        let mut timer = FrameTimer::new(LoopOptions {
+            fixed_dt_ms: Some(10.),
+            max_updates_per_frame: 3,
+            ..Default::default()
+        });
+        assert_eq!(updates(&mut timer, &[0., 5., 15., 35.]), [1, 0, 1, 2]);
+        // A long frame is capped and the rest dropped
+        assert_eq!(updates(&mut timer, &[1000., 1015.]), [3, 1]);
+        assert_eq!(timer.frame(1020.).dt_ms, 10.);
+    }
+
+    #[test]
+    fn frame_cap() {
+        let mut timer = FrameTimer::new(LoopOptions {
+            max_fps: Some(30.),
+            ..Default::default()
+        });
+        let frames = [0., 16.7, 33.3, 50., 66.6, 83.3];
+        assert_eq!(updates(&mut timer, &frames), [1, 0, 1, 0, 1, 0]);
+        assert!((timer.frame(100.).dt_ms - 33.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_updates_per_frame() {
+        let mut timer = FrameTimer::new(LoopOptions {
+            fixed_dt_ms: Some(10.),
+            max_updates_per_frame: 0,
+            ..Default::default()
+        });
+        assert_eq!(updates(&mut timer, &[0., 10., 50.]), [1, 1, 1]);
+    }
+
+    #[test]
+    fn invalid_fixed_dt() {
+        for fixed_dt_ms in [0., -10., f64::NAN] {
+            let mut timer = FrameTimer::new(LoopOptions {
+                fixed_dt_ms: Some(fixed_dt_ms),
+                ..Default::default()
+            });
+            assert_eq!(updates(&mut timer, &[0., 1000. / 60.]), [1, 1]);
+            assert_eq!(timer.frame(2000. / 60.).dt_ms, DEFAULT_STEP_MS);
+        }
+    }
+
+    #[test]
+    fn target_frame_ms() {
+        assert_eq!(LoopOptions::default().target_frame_ms(), 1000. / 60.);
+        let options = LoopOptions {
+            max_fps: Some(20.),
+            ..Default::default()
+        };
+        assert_eq!(options.target_frame_ms(), 50.);
+        let options = LoopOptions {
+            fixed_dt_ms: Some(100.),
+            ..options
+        };
+        assert_eq!(options.target_frame_ms(), 100.);
+    }
+
+    #[test]
+    fn run_updates_then_render_once() {
+        #[derive(Default)]
+        struct Calls {
+            updates: Vec<f64>,
+            renders: Vec<f64>,
+        }
+
+        impl Render for Calls {
+            fn update(&mut self, dt_ms: f64) {
+                self.updates.push(dt_ms);
+            }
+
+            fn render(&mut self, dt_ms: f64) {
+                self.renders.push(dt_ms);
+            }
+
+            fn pixels(&self) -> &[olive_rs::Pixel] {
+                &[]
+            }
+        }
+
+        let mut calls = Calls::default();
+        Frame {
+            dt_ms: 10.,
+            updates: 3,
+        }
+        .run(&mut calls);
+        assert_eq!(calls.updates, [10., 10., 10.]);
+        assert_eq!(calls.renders, [30.]);
+    }
+}
@@ -1,103 +1,640 @@
 use std::{cell::RefCell, rc::Rc};
 
-use olive_rs::{Pixel, Render};
+use olive_rs::{
+    default_font, draw_frame_stats, Canvas, DirtyRect, Event, FrameStats, HeapPixels2D, KeyEvent,
+    Modifiers, Pixel, Pixels2D, PointerButton, Render,
+};
 use wasm_bindgen::prelude::*;
 
-const CANVAS_ID: &str = "app";
+mod frame_timer;
+pub use frame_timer::LoopOptions;
+use frame_timer::{Frame, FrameTimer};
 
-pub fn start_render<R>(w: u32, h: u32, mut render: R)
+const DEFAULT_TITLE: &str = "Olive-rs";
+/// Frames kept in [`FrameStats`]
+const STATS_FRAMES: usize = 120;
+
+/// Where the rendered image is shown
+#[derive(Debug, Clone)]
+pub enum Mount {
+    /// A new canvas appended to `<body>`
+    Body,
+    /// A new canvas appended to this element
+    Container(web_sys::Element),
+    /// An existing canvas
+    Canvas(web_sys::HtmlCanvasElement),
+}
+
+/// The size of the rendered image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanvasSize {
+    Fixed {
+        width: u32,
+        height: u32,
+    },
+    /// The CSS size of the canvas in device pixels, as in [`start_render_resizable`]
+    ///
+    /// A new canvas fills the window for [`Mount::Body`] or its container for [`Mount::Container`],
+    /// while an existing canvas keeps the CSS size given by the page.
+    Fit,
+}
+
+/// Render into a `w` by `h` canvas
+pub fn start_render<R>(w: u32, h: u32, render: R) -> RenderController
+where
+    R: Render + 'static,
+{
+    start_render_on(
+        Mount::Body,
+        CanvasSize::Fixed {
+            width: w,
+            height: h,
+        },
+        render,
+    )
+}
+
+/// Render into a canvas filling the window.
+///
+/// The canvas has one pixel per device pixel for crisp output on high-DPI screens.
+/// [`Event::Resize`] is sent before the first frame and whenever the window size or `devicePixelRatio` changes,
+/// and frames whose pixels do not match the new size are not drawn.
+/// Nothing is rendered while the canvas has no area, and no [`Event::Resize`] is sent for a zero size.
+pub fn start_render_resizable<R>(render: R) -> RenderController
 where
     R: Render + 'static,
 {
-    setup(w, h);
+    start_render_on(Mount::Body, CanvasSize::Fit, render)
+}
 
-    let mut render_time_ms: Option<f64> = None;
+/// Render into the canvas of `mount`.
+///
+/// Each call runs its own independent loop, so several renders can share a page.
+pub fn start_render_on<R>(mount: Mount, size: CanvasSize, render: R) -> RenderController
+where
+    R: Render + 'static,
+{
+    start_render_with(mount, size, LoopOptions::default(), render)
+}
+
+/// [`start_render_on`] with a fixed timestep or a frame-rate cap
+pub fn start_render_with<R>(
+    mount: Mount,
+    size: CanvasSize,
+    loop_options: LoopOptions,
+    render: R,
+) -> RenderController
+where
+    R: Render + 'static,
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    let (canvas, fit_style) = match mount {
+        Mount::Body => {
+            // Only name pages which have no title of their own
+            if document.title().is_empty() {
+                document.set_title(DEFAULT_TITLE);
+            }
+            let canvas = create_canvas(&document);
+            document.body().unwrap().append_child(&canvas).unwrap();
+            (canvas, Some("display: block; width: 100vw; height: 100vh"))
+        }
+        Mount::Container(container) => {
+            let canvas = create_canvas(&document);
+            container.append_child(&canvas).unwrap();
+            (canvas, Some("display: block; width: 100%; height: 100%"))
+        }
+        Mount::Canvas(canvas) => {
+            if !canvas.has_attribute("tabindex") {
+                canvas.set_attribute("tabindex", "0").unwrap();
+            }
+            (canvas, None)
+        }
+    };
+
+    match size {
+        CanvasSize::Fixed { width, height } => {
+            canvas.set_width(width);
+            canvas.set_height(height);
+        }
+        CanvasSize::Fit => {
+            // Keep the CSS size at the window or container size while the backing store follows the device pixels
+            if let Some(style) = fit_style {
+                canvas.set_attribute("style", style).unwrap();
+            }
+        }
+    }
+    start(canvas, size == CanvasSize::Fit, loop_options, render)
+}
+
+/// Controls the loop of a running render.
+///
+/// Dropping it leaves the render running.
+#[derive(Debug, Clone)]
+pub struct RenderController {
+    state: Rc<RefCell<LoopState>>,
+}
+
+#[derive(Debug, Default)]
+struct LoopState {
+    paused: bool,
+    stopped: bool,
+    /// Frames requested by [`RenderController::step`]
+    steps: u32,
+    frame_request: Option<i32>,
+    /// The animation frame callback, which holds the render; emptied once stopped
+    frame_callback: Rc<RefCell<Option<FrameCallback>>>,
+    /// Removed once stopped
+    listeners: Vec<Listener>,
+    stats: FrameStats,
+}
 
-    start_loop(move |timestamp_ms| {
-        let dt_ms = match render_time_ms {
-            Some(t) => timestamp_ms - t,
-            None => 0.,
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+/// A DOM event listener forwarding to [`Render::event`]
+#[derive(Debug)]
+struct Listener {
+    target: web_sys::EventTarget,
+    event_type: &'static str,
+    closure: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl RenderController {
+    /// Stop rendering until [`RenderController::resume`], e.g. while the page is hidden
+    pub fn pause(&self) {
+        self.state.borrow_mut().paused = true;
+    }
+
+    /// The first frame after resuming is rendered like the first frame, so the pause is not part of any `dt_ms`
+    pub fn resume(&self) {
+        self.state.borrow_mut().paused = false;
+    }
+
+    /// Stop the loop for good, e.g. when a component unmounts; the canvas keeps the last frame.
+    ///
+    /// The event listeners are removed and the render is dropped.
+    pub fn stop(&self) {
+        let mut state = self.state.borrow_mut();
+        state.stopped = true;
+        for listener in state.listeners.drain(..) {
+            listener
+                .target
+                .remove_event_listener_with_callback(
+                    listener.event_type,
+                    listener.closure.as_ref().unchecked_ref(),
+                )
+                .unwrap();
+        }
+        // Without a pending frame, the callback is running and drops itself once done
+        let callback = match state.frame_request.take() {
+            Some(id) => {
+                web_sys::window()
+                    .unwrap()
+                    .cancel_animation_frame(id)
+                    .unwrap();
+                state.frame_callback.borrow_mut().take()
+            }
+            None => None,
         };
-        render_time_ms = Some(timestamp_ms);
-        render.render(dt_ms);
+        drop(state);
+        drop(callback);
+    }
+
+    /// Render and draw a single frame on the next animation frame while paused.
+    ///
+    /// `dt_ms` is [`LoopOptions::fixed_dt_ms`] or 1/60 s.
+    pub fn step(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.paused {
+            state.steps += 1;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state.borrow().stopped
+    }
+
+    /// Timing of the frames drawn while running, excluding steps
+    pub fn stats(&self) -> FrameStats {
+        self.state.borrow().stats.clone()
+    }
+}
+
+fn start<R>(
+    canvas: web_sys::HtmlCanvasElement,
+    resizable: bool,
+    loop_options: LoopOptions,
+    render: R,
+) -> RenderController
+where
+    R: Render + 'static,
+{
+    let loop_options = loop_options.validated();
+    let ctx = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap();
+    let render = Rc::new(RefCell::new(render));
+    if !resizable && canvas.width() > 0 && canvas.height() > 0 {
+        render.borrow_mut().event(&Event::Resize {
+            width: canvas.width() as usize,
+            height: canvas.height() as usize,
+        });
+    }
+    let listeners = add_event_listeners(&canvas, &render);
+
+    let state = Rc::new(RefCell::new(LoopState {
+        listeners,
+        stats: FrameStats::new(STATS_FRAMES, loop_options.target_frame_ms()),
+        ..Default::default()
+    }));
+    let mut timer = FrameTimer::new(loop_options);
+    let mut was_paused = false;
+    let mut last_frame_ms: Option<f64> = None;
+    let performance = web_sys::window().unwrap().performance().unwrap();
+    let font = default_font();
+    let mut overlay_pixels = HeapPixels2D::new(0, 0, Pixel::new(0, 0, 0, 0));
+    // Everything is uploaded until a frame of this size has been drawn
+    let mut drawn_size: Option<(u32, u32)> = None;
+    // The size of the last `Event::Resize`, so the first frame with an area always sends one
+    let mut resized_size: Option<(u32, u32)> = None;
+
+    let loop_state = state.clone();
+    start_loop(state.clone(), move |timestamp_ms| {
+        if resizable {
+            fit_device_pixels(&canvas);
+            let size = (canvas.width(), canvas.height());
+            if size.0 > 0 && size.1 > 0 && resized_size != Some(size) {
+                resized_size = Some(size);
+                render.borrow_mut().event(&Event::Resize {
+                    width: size.0 as usize,
+                    height: size.1 as usize,
+                });
+            }
+        }
+        // A collapsed canvas, e.g. under `display: none`, is handled like a pause
+        if canvas.width() == 0 || canvas.height() == 0 {
+            timer.reset();
+            last_frame_ms = None;
+            drawn_size = None;
+            return;
+        }
+
+        let frame = {
+            let mut state = loop_state.borrow_mut();
+            if state.paused {
+                was_paused = true;
+                last_frame_ms = None;
+                if state.steps == 0 {
+                    return;
+                }
+                state.steps -= 1;
+                Frame {
+                    dt_ms: timer.step_dt_ms(),
+                    updates: 1,
+                }
+            } else {
+                if was_paused {
+                    was_paused = false;
+                    timer.reset();
+                }
+                timer.frame(timestamp_ms)
+            }
+        };
+        if frame.updates == 0 {
+            return;
+        }
+
+        let mut render = render.borrow_mut();
+        let render_start_ms = performance.now();
+        frame.run(&mut *render);
+        let mut dirty = render.dirty_rect();
+        let render_ms = performance.now() - render_start_ms;
         let pixels = render.pixels();
 
-        draw(pixels, w, h);
+        if !was_paused {
+            let mut state = loop_state.borrow_mut();
+            if let Some(t) = last_frame_ms {
+                state.stats.record(timestamp_ms - t, render_ms);
+            }
+            last_frame_ms = Some(timestamp_ms);
+        }
+
+        let (w, h) = (canvas.width(), canvas.height());
+        if pixels.len() != w as usize * h as usize {
+            drawn_size = None;
+            return;
+        }
+        if drawn_size != Some((w, h)) {
+            dirty = None;
+        }
+        drawn_size = Some((w, h));
+        match &loop_options.stats_overlay {
+            Some(overlay) => {
+                // Draw over a copy since the pixels belong to the render
+                if (overlay_pixels.width(), overlay_pixels.height()) != (w as usize, h as usize) {
+                    overlay_pixels =
+                        HeapPixels2D::from_pixels(w as usize, h as usize, pixels.to_vec());
+                } else {
+                    overlay_pixels.pixels_mut().copy_from_slice(pixels);
+                }
+                let stats = &loop_state.borrow().stats;
+                draw_frame_stats(
+                    &mut Canvas::new_entire(&mut overlay_pixels),
+                    stats,
+                    &font,
+                    overlay,
+                );
+                draw(&ctx, overlay_pixels.pixels(), w, h, None);
+            }
+            None => draw(&ctx, pixels, w, h, dirty),
+        }
     });
+
+    RenderController { state }
 }
 
+/// Set the backing store of `canvas` to its CSS size in device pixels
+fn fit_device_pixels(canvas: &web_sys::HtmlCanvasElement) {
+    let dpr = web_sys::window().unwrap().device_pixel_ratio();
+    let rect = canvas.get_bounding_client_rect();
+    let width = (rect.width() * dpr).round() as u32;
+    let height = (rect.height() * dpr).round() as u32;
+    // Setting the size clears the canvas even if it is unchanged
+    if (width, height) != (canvas.width(), canvas.height()) {
+        canvas.set_width(width);
+        canvas.set_height(height);
+    }
+}
+
+/// Call `f` on every animation frame until [`LoopState::stopped`], then drop it
+///
 /// Ref: <https://github.com/takahirox/ecs-rust/blob/f62c0a57409c494c4b85e5a320ca5bda74e78c8e/web/examples/canvas_breakout/src/lib.rs#L474-L482>
-fn start_loop<F>(mut f: F)
+fn start_loop<F>(state: Rc<RefCell<LoopState>>, mut f: F)
 where
     F: FnMut(f64) + 'static,
 {
-    let a = Rc::new(RefCell::new(None));
+    // The callback holds itself to request the next frame; the cycle is broken by emptying `a` once stopped
+    let a = state.borrow().frame_callback.clone();
     let b = a.clone();
 
-    fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) {
+    fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) -> i32 {
         web_sys::window()
             .unwrap()
             .request_animation_frame(f.as_ref().unchecked_ref())
-            .unwrap();
+            .unwrap()
     }
 
+    let loop_state = state.clone();
     *b.borrow_mut() = Some(Closure::wrap(Box::new(move |timestamp: f64| {
-        f(timestamp);
-        request_animation_frame(a.borrow().as_ref().unwrap());
+        // `f` may use the controller, so the state is not borrowed while it runs
+        loop_state.borrow_mut().frame_request = None;
+        if !loop_state.borrow().stopped {
+            f(timestamp);
+        }
+        let mut state = loop_state.borrow_mut();
+        if state.stopped {
+            drop(state);
+            // Dropping the running callback is deferred by wasm-bindgen until it returns
+            let _ = a.borrow_mut().take();
+            return;
+        }
+        state.frame_request = Some(request_animation_frame(a.borrow().as_ref().unwrap()));
     }) as Box<dyn FnMut(f64)>));
 
-    request_animation_frame(b.borrow().as_ref().unwrap());
+    state.borrow_mut().frame_request = Some(request_animation_frame(b.borrow().as_ref().unwrap()));
 }
 
-fn setup(w: u32, h: u32) {
-    // Set title
-    web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .set_title("Olive-rs");
+/// A new canvas focusable to receive key events
+fn create_canvas(document: &web_sys::Document) -> web_sys::HtmlCanvasElement {
+    let canvas = document.create_element("canvas").unwrap();
+    canvas.set_attribute("tabindex", "0").unwrap();
+    canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap()
+}
 
-    // Create canvas
-    let canvas = web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .create_element("canvas")
-        .unwrap();
-    canvas.set_id(CANVAS_ID);
-    let canvas = canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-    canvas.set_width(w);
-    canvas.set_height(h);
+/// Forward DOM events on `canvas` to [`Render::event`] until the returned listeners are removed
+fn add_event_listeners<R>(
+    canvas: &web_sys::HtmlCanvasElement,
+    render: &Rc<RefCell<R>>,
+) -> Vec<Listener>
+where
+    R: Render + 'static,
+{
+    fn listen<E, F>(
+        listeners: &mut Vec<Listener>,
+        canvas: &web_sys::HtmlCanvasElement,
+        event_type: &'static str,
+        mut f: F,
+    ) where
+        E: JsCast,
+        F: FnMut(E) + 'static,
+    {
+        let closure = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            f(event.unchecked_into::<E>());
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        canvas
+            .add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+            .unwrap();
+        listeners.push(Listener {
+            target: canvas.clone().into(),
+            event_type,
+            closure,
+        });
+    }
 
-    // Append the canvas to the DOM
-    web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .body()
-        .unwrap()
-        .append_child(&canvas)
-        .unwrap();
+    /// From CSS pixels in the viewport to pixels of the canvas
+    fn canvas_position(
+        canvas: &web_sys::HtmlCanvasElement,
+        event: &web_sys::MouseEvent,
+    ) -> (f64, f64) {
+        let rect = canvas.get_bounding_client_rect();
+        let scale_x = canvas.width() as f64 / rect.width().max(1.);
+        let scale_y = canvas.height() as f64 / rect.height().max(1.);
+        let x = (event.client_x() as f64 - rect.left()) * scale_x;
+        let y = (event.client_y() as f64 - rect.top()) * scale_y;
+        (x, y)
+    }
+
+    /// - Ref: <https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent/button>
+    fn pointer_button(event: &web_sys::MouseEvent) -> PointerButton {
+        match event.button() {
+            0 => PointerButton::Primary,
+            1 => PointerButton::Middle,
+            2 => PointerButton::Secondary,
+            b => PointerButton::Other(b as u16),
+        }
+    }
+
+    fn key_event(event: &web_sys::KeyboardEvent) -> KeyEvent {
+        KeyEvent {
+            key: event.key(),
+            code: event.code(),
+            repeat: event.repeat(),
+            modifiers: Modifiers {
+                shift: event.shift_key(),
+                ctrl: event.ctrl_key(),
+                alt: event.alt_key(),
+                meta: event.meta_key(),
+            },
+        }
+    }
+
+    let mut listeners = Vec::new();
+    let dispatch = {
+        let render = render.clone();
+        Rc::new(move |event: Event| render.borrow_mut().event(&event))
+    };
+
+    {
+        let c = canvas.clone();
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "pointermove",
+            move |e: web_sys::PointerEvent| {
+                let (x, y) = canvas_position(&c, &e);
+                dispatch(Event::PointerMove { x, y });
+            },
+        );
+    }
+    {
+        let c = canvas.clone();
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "pointerdown",
+            move |e: web_sys::PointerEvent| {
+                // Keep receiving the pointer events while dragging outside the canvas
+                let _ = c.set_pointer_capture(e.pointer_id());
+                let (x, y) = canvas_position(&c, &e);
+                let button = pointer_button(&e);
+                dispatch(Event::PointerDown { x, y, button });
+            },
+        );
+    }
+    {
+        let c = canvas.clone();
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "pointerup",
+            move |e: web_sys::PointerEvent| {
+                let (x, y) = canvas_position(&c, &e);
+                let button = pointer_button(&e);
+                dispatch(Event::PointerUp { x, y, button });
+            },
+        );
+    }
+    {
+        let c = canvas.clone();
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "wheel",
+            move |e: web_sys::WheelEvent| {
+                // Do not scroll the page
+                e.prevent_default();
+                // Convert lines and pages to pixels
+                // - Ref: <https://developer.mozilla.org/en-US/docs/Web/API/WheelEvent/deltaMode>
+                let scale = match e.delta_mode() {
+                    web_sys::WheelEvent::DOM_DELTA_LINE => 16.,
+                    web_sys::WheelEvent::DOM_DELTA_PAGE => c.height() as f64,
+                    _ => 1.,
+                };
+                dispatch(Event::Wheel {
+                    dx: e.delta_x() * scale,
+                    dy: e.delta_y() * scale,
+                });
+            },
+        );
+    }
+    {
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "keydown",
+            move |e: web_sys::KeyboardEvent| {
+                dispatch(Event::KeyDown(key_event(&e)));
+            },
+        );
+    }
+    {
+        let dispatch = dispatch.clone();
+        listen(
+            &mut listeners,
+            canvas,
+            "keyup",
+            move |e: web_sys::KeyboardEvent| {
+                dispatch(Event::KeyUp(key_event(&e)));
+            },
+        );
+    }
+    {
+        let dispatch = dispatch.clone();
+        listen(&mut listeners, canvas, "focus", move |_: web_sys::Event| {
+            dispatch(Event::Focus(true));
+        });
+    }
+    listen(&mut listeners, canvas, "blur", move |_: web_sys::Event| {
+        dispatch(Event::Focus(false));
+    });
+    listeners
 }
 
-fn draw(pixels: &[Pixel], w: u32, h: u32) {
-    let app = web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .get_element_by_id(CANVAS_ID)
+/// Upload the `dirty` region of `pixels`, or all of them for `None`
+fn draw(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    pixels: &[Pixel],
+    w: u32,
+    h: u32,
+    dirty: Option<DirtyRect>,
+) {
+    fn as_bytes(pixels: &[Pixel]) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) }
+    }
+
+    fn put(ctx: &web_sys::CanvasRenderingContext2d, bytes: &[u8], rect: DirtyRect) {
+        let img_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
+            wasm_bindgen::Clamped(bytes),
+            rect.width as u32,
+            rect.height as u32,
+        )
         .unwrap();
-    let app = app.dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-    let ctx = app.get_context("2d").unwrap().unwrap();
-    let ctx = ctx.dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
-
-    let pixels_u8 =
-        unsafe { std::slice::from_raw_parts(pixels.as_ptr() as *const u8, pixels.len() * 4) };
-    let img_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
-        wasm_bindgen::Clamped(pixels_u8),
-        w,
-        h,
-    )
-    .unwrap();
-    ctx.put_image_data(&img_data, 0.0, 0.0).unwrap();
+        ctx.put_image_data(&img_data, rect.x as f64, rect.y as f64)
+            .unwrap();
+    }
+
+    let (w, h) = (w as usize, h as usize);
+    let full = DirtyRect::new(0, 0, w, h);
+    let Some(dirty) = dirty else {
+        put(ctx, as_bytes(pixels), full);
+        return;
+    };
+    // Clip to the canvas
+    let x = dirty.x.min(w);
+    let y = dirty.y.min(h);
+    let rect = DirtyRect::new(x, y, dirty.width.min(w - x), dirty.height.min(h - y));
+    if rect.is_empty() {
+        return;
+    }
+    if rect == full {
+        put(ctx, as_bytes(pixels), full);
+        return;
+    }
+    let mut bytes = Vec::with_capacity(rect.width * rect.height * 4);
+    for row in rect.y..rect.y + rect.height {
+        let start = row * w + rect.x;
+        bytes.extend_from_slice(as_bytes(&pixels[start..start + rect.width]));
+    }
+    put(ctx, &bytes, rect);
 }